@@ -0,0 +1,192 @@
+// code that compiles on stable but is rejected on master (or the other way around),
+// see --accept-reject
+//
+// there is no crash to look for, every file is checked on every edition with the rustc of both
+// toolchains and we compare whether it was accepted. Files that enable unstable features are
+// skipped, features change all the time on nightly and breaking them is not a regression.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use tempdir::TempDir;
+
+use crate::editions::{edition_year, EDITION_FLAGS};
+use crate::ice::{
+    AcceptReject, AcceptanceChange, Executable, ICEKind, Regression, HOST_TRIPLE, ICE,
+};
+use crate::run_commands::prlimit_run_command;
+use crate::triage::enabled_features;
+
+// stable does not know the edition yet, master accepting the file on it is no news
+static UNKNOWN_EDITION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"error: (edition \d+ is unstable|argument for `--edition` must be one of)").unwrap()
+});
+
+/// Ok if rustc accepts the file, its diagnostics otherwise.
+/// None if rustc crashed, timed out or could not be run, crashes are found by the regular runs
+fn check(
+    rustc: &Path,
+    file: &Path,
+    has_main: bool,
+    edition_flag: &str,
+    tempdir: &Path,
+) -> Option<Result<(), String>> {
+    let output = prlimit_run_command(
+        Command::new(rustc)
+            .arg(file)
+            .arg(edition_flag)
+            .arg(if has_main {
+                "--crate-type=bin"
+            } else {
+                "--crate-type=lib"
+            })
+            .arg("--emit=metadata")
+            .args(["--cap-lints", "warn"])
+            .env("CARGO_TERM_COLOR", "never")
+            .current_dir(tempdir),
+    )
+    .ok()?;
+    match output.status.code() {
+        Some(0) => Some(Ok(())),
+        Some(1) => Some(Err(String::from_utf8_lossy(&output.stderr).to_string())),
+        // 101 is an ICE
+        _ => None,
+    }
+}
+
+/// the accept/reject change on the edition, if there is one
+fn compare_edition(
+    stable: &Path,
+    master: &Path,
+    file: &Path,
+    has_main: bool,
+    edition_flag: &str,
+    tempdir: &Path,
+) -> Option<AcceptReject> {
+    let on_stable = check(stable, file, has_main, edition_flag, tempdir)?;
+    let on_master = check(master, file, has_main, edition_flag, tempdir)?;
+    match (on_stable, on_master) {
+        (Ok(()), Err(master_diagnostics)) => Some(AcceptReject {
+            change: AcceptanceChange::NewlyRejected,
+            stable_diagnostics: String::new(),
+            master_diagnostics,
+        }),
+        (Err(stable_diagnostics), Ok(()))
+            if !UNKNOWN_EDITION_REGEX.is_match(&stable_diagnostics) =>
+        {
+            Some(AcceptReject {
+                change: AcceptanceChange::NewlyAccepted,
+                stable_diagnostics,
+                master_diagnostics: String::new(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn first_error(diagnostics: &str) -> String {
+    diagnostics
+        .lines()
+        .find(|line| line.starts_with("error"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// one finding per file, for the oldest edition whose result changed
+fn compare_file(
+    stable: &Path,
+    master: &Path,
+    file: &Path,
+    global_tempdir_path: &Path,
+) -> Option<ICE> {
+    let source = std::fs::read_to_string(file).ok()?;
+    if !enabled_features(&source).is_empty() {
+        return None;
+    }
+    let has_main = source.contains("fn main(");
+    let file = file.canonicalize().ok()?;
+    let tempdir = TempDir::new_in(global_tempdir_path, "icemaker_accept_reject_tempdir").ok()?;
+
+    let changes = EDITION_FLAGS
+        .iter()
+        .filter_map(|edition_flag| {
+            compare_edition(
+                stable,
+                master,
+                &file,
+                has_main,
+                edition_flag,
+                tempdir.path(),
+            )
+            .map(|change| (edition_year(edition_flag), change))
+        })
+        .collect::<Vec<_>>();
+
+    let (edition, accept_reject) = changes.first()?.clone();
+    let (error, ice_msg) = match accept_reject.change {
+        AcceptanceChange::NewlyRejected => {
+            let error = first_error(&accept_reject.master_diagnostics);
+            (error.clone(), format!("newly rejected: {error}"))
+        }
+        AcceptanceChange::NewlyAccepted => {
+            let error = first_error(&accept_reject.stable_diagnostics);
+            (error.clone(), format!("newly accepted: {error}"))
+        }
+    };
+    let editions = changes
+        .iter()
+        .filter(|(_, other)| other.change == accept_reject.change)
+        .map(|(edition, _)| edition.to_string())
+        .collect();
+
+    Some(ICE {
+        regresses_on: Regression::Master,
+        needs_feature: false,
+        file,
+        args: vec![format!("--edition={edition}")],
+        error_reason: error,
+        ice_msg,
+        executable: Executable::Rustc,
+        query_stack: Vec::new(),
+        kind: ICEKind::AcceptReject(accept_reject),
+        editions,
+        ..Default::default()
+    })
+}
+
+/// check all files on stable and on master, the ones that are accepted by only one of them
+pub(crate) fn accept_reject_findings(files: &[PathBuf], global_tempdir_path: &Path) -> Vec<ICE> {
+    let Some(stable) = Executable::Rustc
+        .path_in_toolchain(&format!("stable-{}", *HOST_TRIPLE))
+        .filter(|path| path.exists())
+    else {
+        eprintln!("--accept-reject needs a stable-{} toolchain", *HOST_TRIPLE);
+        return Vec::new();
+    };
+    let master = PathBuf::from(Executable::Rustc.path());
+
+    files
+        .par_iter()
+        .filter_map(|file| compare_file(&stable, &master, file, global_tempdir_path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_edition() {
+        assert!(UNKNOWN_EDITION_REGEX.is_match(
+            "error: edition 2024 is unstable and only available with -Z unstable-options"
+        ));
+        assert!(!UNKNOWN_EDITION_REGEX.is_match("error[E0308]: mismatched types"));
+        assert_eq!(
+            first_error("warning: unused variable\nerror[E0308]: mismatched types\n"),
+            "error[E0308]: mismatched types"
+        );
+    }
+}
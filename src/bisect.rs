@@ -0,0 +1,281 @@
+// find the nightly that introduced a finding, without network access
+//
+// cargo-bisect-rustc downloads toolchains, we only look at the dated nightlies that are already
+// installed (nightly-2024-01-31-x86_64-unknown-linux-gnu ..) and binary search over them.
+
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tempdir::TempDir;
+
+use crate::ice::{panic_location_from_str, Executable, ICE};
+use crate::reduce::{comparable_msg, is_reducible, oracle_flags};
+use crate::run_commands::{parallel_slots, run_executable};
+use crate::{find_ICE_string, UNSUPPORTED_FLAG_REGEX};
+
+static DATED_NIGHTLY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^nightly-(\d{4}-\d{2}-\d{2})").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toolchain {
+    // nightly-2024-01-31
+    pub(crate) name: String,
+    pub(crate) commit_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegressionWindow {
+    // None if even the oldest installed nightly reproduces the finding
+    pub(crate) last_good: Option<Toolchain>,
+    pub(crate) first_bad: Toolchain,
+}
+
+impl std::fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match &self.commit_hash {
+            Some(hash) => write!(f, "{} ({hash})", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl std::fmt::Display for RegressionWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match &self.last_good {
+            Some(last_good) => write!(f, "last good {last_good}, first bad {}", self.first_bad),
+            None => write!(
+                f,
+                "first bad {}, already reproduces on the oldest installed nightly",
+                self.first_bad
+            ),
+        }
+    }
+}
+
+impl RegressionWindow {
+    /// link to the commits between last good and first bad
+    pub(crate) fn compare_url(&self) -> Option<String> {
+        let good = self.last_good.as_ref()?.commit_hash.as_ref()?;
+        let bad = self.first_bad.commit_hash.as_ref()?;
+        Some(format!(
+            "https://github.com/rust-lang/rust/compare/{good}...{bad}"
+        ))
+    }
+}
+
+/// an installed dated nightly
+#[derive(Debug, Clone)]
+struct InstalledNightly {
+    date: String,
+    dir: PathBuf,
+}
+
+impl InstalledNightly {
+    fn binary(&self, executable: &Executable) -> Option<PathBuf> {
+        Some(self.dir.join("bin").join(executable.binary_name()?))
+    }
+
+    fn toolchain(&self) -> Toolchain {
+        Toolchain::new(format!("nightly-{}", self.date), &self.dir)
+    }
+}
+
+impl Toolchain {
+    /// the toolchain inside the dir, asks its rustc for the commit it was built from
+    pub(crate) fn new(name: String, dir: &Path) -> Self {
+        let commit_hash = std::process::Command::new(dir.join("bin").join("rustc"))
+            .arg("-vV")
+            .output()
+            .ok()
+            .and_then(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .find_map(|line| line.strip_prefix("commit-hash: "))
+                    .map(|hash| hash.trim().to_string())
+            });
+        Toolchain { name, commit_hash }
+    }
+}
+
+/// all dated nightlies inside the dir, oldest first
+fn installed_nightlies(toolchains_dir: &Path) -> Vec<InstalledNightly> {
+    let mut nightlies = std::fs::read_dir(toolchains_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let date = DATED_NIGHTLY_REGEX.captures(&name)?[1].to_string();
+            Some(InstalledNightly {
+                date,
+                dir: entry.path(),
+            })
+        })
+        .collect::<Vec<InstalledNightly>>();
+    nightlies.sort_by(|a, b| a.date.cmp(&b.date));
+    // nightlies for several hosts
+    nightlies.dedup_by(|a, b| a.date == b.date);
+    nightlies
+}
+
+/// indices of the last good and the first bad element, assuming that every element after a bad one
+/// is bad as well. `is_bad` is None for elements that we can not tell (a nightly that does not know
+/// a flag), they are skipped and may end up between last good and first bad.
+/// None if the last element that we can tell is not bad
+fn first_bad<T>(
+    items: &[T],
+    is_bad: impl Fn(&T) -> Option<bool>,
+) -> Option<(Option<usize>, usize)> {
+    let newest = items.iter().rposition(|item| is_bad(item).is_some())?;
+    if is_bad(&items[newest]) != Some(true) {
+        return None;
+    }
+    // items[good] is good (or -1), items[bad] is bad
+    let (mut good, mut bad) = (-1_isize, newest as isize);
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+        // the closest element to the middle that we can tell
+        let known = (0..bad - good)
+            .flat_map(|distance| [mid + distance, mid - distance])
+            .filter(|index| good < *index && *index < bad)
+            .find_map(|index| is_bad(&items[index as usize]).map(|is_bad| (index, is_bad)));
+        match known {
+            Some((index, true)) => bad = index,
+            Some((index, false)) => good = index,
+            None => break,
+        }
+    }
+    Some(((good >= 0).then_some(good as usize), bad as usize))
+}
+
+/// does the binary of another toolchain crash the same way?
+pub(crate) fn reproduces_on(ice: &ICE, binary: &Path, file: &Path, dir: &Path) -> bool {
+    reproduction_on(ice, binary, file, dir) == Some(true)
+}
+
+/// None if the binary does not know one of the flags of the finding.
+/// line numbers of the panic location change between nightlies, only compare the file
+fn reproduction_on(ice: &ICE, binary: &Path, file: &Path, dir: &Path) -> Option<bool> {
+    let (compiler_flags, miri_flags) = oracle_flags(ice);
+    let (output, _cmd, _actual_args) = run_executable(
+        &ice.executable,
+        &binary.display().to_string(),
+        file,
+        false,
+        &compiler_flags,
+        &miri_flags,
+        &dir.to_path_buf(),
+    )
+    .unwrap();
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stderr),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let Some((msg, _kind, _query_stack)) = find_ICE_string(file, &ice.executable, output) else {
+        return (!UNSUPPORTED_FLAG_REGEX.is_match(&text)).then_some(false);
+    };
+
+    let same_panic_file = match ice.panic_location() {
+        Some(location) => panic_location_from_str(&text)
+            .is_some_and(|found| found.split(':').next() == location.split(':').next()),
+        None => true,
+    };
+    Some(same_panic_file && comparable_msg(&msg) == comparable_msg(&ice.ice_msg))
+}
+
+/// binary search for the first installed nightly that reproduces the finding
+pub(crate) fn bisect_ice(
+    ice: &ICE,
+    toolchains_dir: &Path,
+    global_tempdir_path: &Path,
+) -> Option<RegressionWindow> {
+    if !is_reducible(ice) {
+        return None;
+    }
+    let nightlies = installed_nightlies(toolchains_dir)
+        .into_iter()
+        .filter(|nightly| {
+            nightly
+                .binary(&ice.executable)
+                .is_some_and(|binary| binary.exists())
+        })
+        .collect::<Vec<_>>();
+    let file = ice.file.canonicalize().ok()?;
+    let tempdir = TempDir::new_in(global_tempdir_path, "icemaker_bisection_tempdir").ok()?;
+
+    let (last_good, first_bad) = first_bad(&nightlies, |nightly| {
+        let binary = nightly.binary(&ice.executable).unwrap();
+        reproduction_on(ice, &binary, &file, tempdir.path())
+    })?;
+
+    Some(RegressionWindow {
+        last_good: last_good.map(|last_good| nightlies[last_good].toolchain()),
+        first_bad: nightlies[first_bad].toolchain(),
+    })
+}
+
+/// bisect all findings of the errors.json, findings that were bisected already are skipped
+pub(crate) fn bisect_findings(
+    ices: Vec<ICE>,
+    toolchains_dir: &Path,
+    global_tempdir_path: &Path,
+) -> Vec<ICE> {
+    let nightlies = installed_nightlies(toolchains_dir);
+    eprintln!(
+        "bisecting {} findings over {} nightlies in {}",
+        ices.iter()
+            .filter(|ice| ice.regression_window.is_none())
+            .count(),
+        nightlies.len(),
+        toolchains_dir.display()
+    );
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(parallel_slots())
+        .build()
+        .expect("failed to build thread pool for bisection");
+
+    pool.install(|| {
+        ices.into_par_iter()
+            .map(|mut ice| {
+                if ice.regression_window.is_none() {
+                    ice.regression_window = bisect_ice(&ice, toolchains_dir, global_tempdir_path);
+                    if let Some(window) = &ice.regression_window {
+                        eprintln!("{}: {window}", ice.file.display());
+                    }
+                }
+                ice
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_search() {
+        let nightlies = (0..20).collect::<Vec<u32>>();
+        assert_eq!(
+            first_bad(&nightlies, |n| Some(*n >= 13)),
+            Some((Some(12), 13))
+        );
+        assert_eq!(first_bad(&nightlies, |_| Some(true)), Some((None, 0)));
+        assert_eq!(first_bad(&nightlies, |_| Some(false)), None);
+        assert_eq!(first_bad(&[] as &[u32], |_| Some(true)), None);
+
+        // nightlies that do not know a flag are neither good nor bad
+        let unknown = |n: &u32| (!(10..15).contains(n)).then_some(*n >= 13);
+        assert_eq!(first_bad(&nightlies, unknown), Some((Some(9), 15)));
+        let unknown = |n: &u32| (*n != 12).then_some(*n >= 13);
+        assert_eq!(first_bad(&nightlies, unknown), Some((Some(11), 13)));
+        let unknown = |n: &u32| (*n < 18).then_some(*n >= 13);
+        assert_eq!(first_bad(&nightlies, unknown), Some((Some(12), 13)));
+        assert_eq!(first_bad(&nightlies, |_| None), None);
+    }
+}
@@ -0,0 +1,120 @@
+// does a finding need debug assertions to crash?
+//
+// --local-debug-assertions replaces master instead of running alongside it, but a crash that only
+// happens with debug assertions (a debug_assert! or a span check) is treated very differently from
+// one that also happens in a release build. The reproduction matrix of a finding already ran it on
+// both builds.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ice::{ToolchainOutcome, ICE};
+use crate::reduce::is_reducible;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssertionSensitivity {
+    // crashes on the debug assertions build only
+    DebugAssertionsOnly,
+    // crashes on the release build only
+    ReleaseOnly,
+    Both,
+}
+
+impl std::fmt::Display for AssertionSensitivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let s = match self {
+            AssertionSensitivity::DebugAssertionsOnly => "only with debug assertions",
+            AssertionSensitivity::ReleaseOnly => "only without debug assertions",
+            AssertionSensitivity::Both => "with and without debug assertions",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl AssertionSensitivity {
+    fn from_outcomes(release: bool, debug_assertions: bool) -> Option<Self> {
+        match (release, debug_assertions) {
+            (true, true) => Some(AssertionSensitivity::Both),
+            (true, false) => Some(AssertionSensitivity::ReleaseOnly),
+            (false, true) => Some(AssertionSensitivity::DebugAssertionsOnly),
+            // flaky, or the finding needs something that we do not pass here
+            (false, false) => None,
+        }
+    }
+}
+
+/// None if the toolchain could not tell
+fn crashes(outcome: ToolchainOutcome) -> Option<bool> {
+    match outcome {
+        ToolchainOutcome::Ice => Some(true),
+        ToolchainOutcome::CompileError | ToolchainOutcome::Success => Some(false),
+        ToolchainOutcome::FlagsUnsupported | ToolchainOutcome::NotInstalled => None,
+    }
+}
+
+impl ICE {
+    /// take the outcomes on the release and the debug assertions build of master from the
+    /// reproduction matrix, unless we already know the result. Stays None without a matrix or if
+    /// one of them is not installed
+    pub(crate) fn with_debug_assertions(mut self) -> Self {
+        if self.debug_assertions.is_some() || !is_reducible(&self) {
+            return self;
+        }
+        self.debug_assertions = self.regression_detailed.as_ref().and_then(|matrix| {
+            AssertionSensitivity::from_outcomes(
+                crashes(matrix.master)?,
+                crashes(matrix.local_debug_assertions)?,
+            )
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ice::RegressionDetailed;
+
+    #[test]
+    fn outcomes() {
+        assert_eq!(
+            AssertionSensitivity::from_outcomes(false, true),
+            Some(AssertionSensitivity::DebugAssertionsOnly)
+        );
+        assert_eq!(
+            AssertionSensitivity::from_outcomes(true, false),
+            Some(AssertionSensitivity::ReleaseOnly)
+        );
+        assert_eq!(AssertionSensitivity::from_outcomes(false, false), None);
+    }
+
+    #[test]
+    fn from_matrix() {
+        let with_matrix = |master, local_debug_assertions| {
+            ICE {
+                regression_detailed: Some(RegressionDetailed {
+                    host: String::new(),
+                    stable: ToolchainOutcome::Success,
+                    beta: ToolchainOutcome::Success,
+                    nightly: ToolchainOutcome::Success,
+                    master,
+                    local_debug_assertions,
+                }),
+                ..Default::default()
+            }
+            .with_debug_assertions()
+            .debug_assertions
+        };
+        assert_eq!(
+            with_matrix(ToolchainOutcome::CompileError, ToolchainOutcome::Ice),
+            Some(AssertionSensitivity::DebugAssertionsOnly)
+        );
+        assert_eq!(
+            with_matrix(ToolchainOutcome::Ice, ToolchainOutcome::NotInstalled),
+            None
+        );
+        assert_eq!(
+            ICE::default().with_debug_assertions().debug_assertions,
+            None
+        );
+    }
+}
@@ -0,0 +1,213 @@
+// match findings against a local json export of the rust-lang/rust issue tracker so that we
+// do not have to search for every new ICE by hand before filing it.
+//
+// the dump is expected to be what the github issues api returns: a json array of
+// { "number": 123, "title": "...", "body": "...", ... } objects
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use clap::Parser;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::ice::{normalize_ice_msg, panic_location_from_str, query_frames_from_lines, ICE};
+use crate::library::Args;
+
+/// everything below this is most likely just noise
+const DUPLICATE_THRESHOLD: f64 = 0.5;
+
+pub(crate) static ISSUE_DUMP: Lazy<Option<IssueDump>> =
+    Lazy::new(|| Args::parse().issue_dump.map(|path| IssueDump::load(&path)));
+
+#[derive(Debug, Deserialize)]
+struct RawIssue {
+    number: u64,
+    #[serde(default)]
+    title: String,
+    // body can be null
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PreparedIssue {
+    number: u64,
+    locations: Vec<String>,
+    msg_tokens: HashSet<String>,
+    query_frames: HashSet<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct IssueDump {
+    issues: Vec<PreparedIssue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PossibleDuplicate {
+    pub(crate) number: u64,
+    pub(crate) similarity: f64,
+}
+
+impl std::fmt::Display for PossibleDuplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "possible duplicate of #{} (similarity: {:.2})",
+            self.number, self.similarity
+        )
+    }
+}
+
+fn tokens(normalized_msg: &str) -> HashSet<String> {
+    normalized_msg
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|token| token.len() >= 3)
+        .map(str::to_string)
+        .collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / a.union(b).count() as f64
+}
+
+/// compiler/rustc_foo/src/bar.rs:1:2 => compiler/rustc_foo/src/bar.rs
+fn location_file(location: &str) -> &str {
+    location.split(".rs:").next().unwrap_or(location)
+}
+
+impl PreparedIssue {
+    fn new(raw: RawIssue) -> Self {
+        let body = raw.body.unwrap_or_default();
+        let text = format!("{}\n{}", raw.title, body);
+
+        let mut locations = text
+            .lines()
+            .filter_map(panic_location_from_str)
+            .collect::<Vec<String>>();
+        locations.dedup();
+
+        // the title and every line that looks like it is part of the ice message,
+        // since rust 1.73 the actual panic message is on the line after "panicked at"
+        let mut ice_lines = vec![raw.title.as_str()];
+        let mut lines = body.lines().peekable();
+        while let Some(line) = lines.next() {
+            if line.contains("panicked at") {
+                ice_lines.push(line);
+                if let Some(next) = lines.peek() {
+                    ice_lines.push(next);
+                }
+            } else if line.contains("internal compiler error") || line.contains("assertion") {
+                ice_lines.push(line);
+            }
+        }
+        let msg_tokens = tokens(&normalize_ice_msg(&ice_lines.join(" ")));
+
+        let query_frames = query_frames_from_lines(body.lines())
+            .into_iter()
+            .collect::<HashSet<String>>();
+
+        PreparedIssue {
+            number: raw.number,
+            locations,
+            msg_tokens,
+            query_frames,
+        }
+    }
+
+    fn similarity(
+        &self,
+        location: Option<&str>,
+        msg_tokens: &HashSet<String>,
+        query_frames: &HashSet<String>,
+    ) -> f64 {
+        let location_score = match location {
+            Some(location) if self.locations.iter().any(|l| l == location) => 1.0,
+            Some(location)
+                if self
+                    .locations
+                    .iter()
+                    .any(|l| location_file(l) == location_file(location)) =>
+            {
+                0.5
+            }
+            _ => 0.0,
+        };
+
+        0.45 * location_score
+            + 0.35 * jaccard(&self.msg_tokens, msg_tokens)
+            + 0.2 * jaccard(&self.query_frames, query_frames)
+    }
+}
+
+impl IssueDump {
+    pub(crate) fn load(path: &Path) -> Self {
+        let content = std::fs::read_to_string(path)
+            .unwrap_or_else(|_| panic!("failed to read issue dump '{}'", path.display()));
+        let raw_issues: Vec<RawIssue> = serde_json::from_str(&content)
+            .unwrap_or_else(|e| panic!("failed to parse issue dump '{}': {e:?}", path.display()));
+
+        IssueDump {
+            issues: raw_issues.into_iter().map(PreparedIssue::new).collect(),
+        }
+    }
+
+    /// the most similar issue from the dump, if it is similar enough
+    pub(crate) fn best_match(&self, ice: &ICE) -> Option<PossibleDuplicate> {
+        let location = ice.panic_location();
+        let msg_tokens = tokens(&ice.normalized_msg());
+        let query_frames = ice.query_frames().into_iter().collect::<HashSet<String>>();
+
+        self.issues
+            .iter()
+            .map(|issue| PossibleDuplicate {
+                number: issue.number,
+                similarity: issue.similarity(location.as_deref(), &msg_tokens, &query_frames),
+            })
+            .filter(|dup| dup.similarity >= DUPLICATE_THRESHOLD)
+            .max_by(|a, b| a.similarity.total_cmp(&b.similarity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(number: u64, title: &str, body: &str) -> PreparedIssue {
+        PreparedIssue::new(RawIssue {
+            number,
+            title: title.into(),
+            body: Some(body.into()),
+        })
+    }
+
+    #[test]
+    fn same_location_and_message_is_duplicate() {
+        let known = issue(
+            12345,
+            "ICE: `expected type, found const`",
+            "thread 'rustc' panicked at compiler/rustc_hir_typeck/src/expr.rs:123:45:\nexpected type, found const\nquery stack during panic:\n#0 [typeck] type-checking `main`\nend of query stack",
+        );
+        let location = Some("compiler/rustc_hir_typeck/src/expr.rs:123:45");
+        let msg_tokens = tokens(&normalize_ice_msg("thread 'rustc' panicked at compiler/rustc_hir_typeck/src/expr.rs:123:45: 'expected type, found const'"));
+        let frames = HashSet::from([String::from("typeck")]);
+
+        assert!(known.similarity(location, &msg_tokens, &frames) > 0.9);
+    }
+
+    #[test]
+    fn unrelated_issue_is_not_duplicate() {
+        let known = issue(
+            1,
+            "rustdoc: broken link in docs",
+            "the link to foo does not work",
+        );
+        let location = Some("compiler/rustc_hir_typeck/src/expr.rs:123:45");
+        let msg_tokens = tokens(&normalize_ice_msg("expected type, found const"));
+
+        assert!(known.similarity(location, &msg_tokens, &HashSet::new()) < DUPLICATE_THRESHOLD);
+    }
+}
@@ -0,0 +1,98 @@
+// on which editions does a finding reproduce?
+//
+// a crash that only happens on some editions is usually caused by edition dependent lowering or
+// name resolution and should be labeled as such, reports use the lowest edition that reproduces
+
+use std::path::Path;
+
+use rayon::prelude::*;
+use tempdir::TempDir;
+
+use crate::ice::{Executable, ICE};
+use crate::reduce::{candidate_is_interesting, is_reducible};
+
+/// every edition that rustc supports, oldest first
+pub(crate) static EDITION_FLAGS: &[&str] = &[
+    "--edition=2015",
+    "--edition=2018",
+    "--edition=2021",
+    "--edition=2024",
+];
+
+pub(crate) fn edition_year(flag: &str) -> &str {
+    flag.trim_start_matches("--edition=")
+}
+
+/// replace the --edition of the flags or add one
+pub(crate) fn with_edition(args: &[String], edition: &str) -> Vec<String> {
+    args.iter()
+        .filter(|arg| !arg.starts_with("--edition="))
+        .cloned()
+        .chain(std::iter::once(format!("--edition={edition}")))
+        .collect()
+}
+
+/// rustfmt, rustdoc and clippy are always run with a fixed edition
+fn honors_edition_flag(executable: &Executable) -> bool {
+    matches!(
+        executable,
+        Executable::Rustc | Executable::Miri | Executable::Cranelift | Executable::RustcCodegenGCC
+    )
+}
+
+impl ICE {
+    /// run the finding on every edition and remember the ones that still reproduce it
+    pub(crate) fn with_editions(mut self, global_tempdir_path: &Path) -> Self {
+        if !honors_edition_flag(&self.executable) || !is_reducible(&self) {
+            return self;
+        }
+        let Ok(file) = self.file.canonicalize() else {
+            return self;
+        };
+        let Ok(tempdir) = TempDir::new_in(global_tempdir_path, "icemaker_editions_tempdir") else {
+            return self;
+        };
+
+        self.editions = EDITION_FLAGS
+            .par_iter()
+            .map(|flag| edition_year(flag))
+            .filter(|edition| {
+                let ice = ICE {
+                    args: with_edition(&self.args, edition),
+                    ..self.clone()
+                };
+                candidate_is_interesting(&ice, &file, tempdir.path(), false)
+            })
+            .map(str::to_string)
+            .collect();
+        self
+    }
+
+    /// the oldest edition the finding reproduces on
+    pub(crate) fn lowest_edition(&self) -> Option<&str> {
+        self.editions.first().map(String::as_str)
+    }
+
+    /// reproduces on some, but not all editions
+    pub(crate) fn is_edition_specific(&self) -> bool {
+        !self.editions.is_empty() && self.editions.len() < EDITION_FLAGS.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_edition() {
+        let args = vec![
+            String::from("-Zmir-opt-level=3"),
+            String::from("--edition=2021"),
+        ];
+        assert_eq!(
+            with_edition(&args, "2015"),
+            ["-Zmir-opt-level=3", "--edition=2015"]
+        );
+        assert_eq!(with_edition(&[], "2018"), ["--edition=2018"]);
+    }
+}
@@ -0,0 +1,225 @@
+// find the smallest set of flags (and the smallest flag values) that still reproduce a crash
+//
+// delta debugging (ddmin) instead of removing one flag at a time: chunks of flags are removed at
+// once and only split further if that fails. Every subset is only tested once and candidates of a
+// round are run in parallel on the global thread pool, next to the files that are being checked.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+
+use crate::triage::enabled_features;
+
+// only try to lower values that look like levels, not thresholds like -Zinline-mir-threshold=1000
+const MAX_MINIMIZED_VALUE: u32 = 10;
+
+/// remembers the outcome of every set of flags that was already tested
+struct CachedTest<F> {
+    test: F,
+    cache: Mutex<HashMap<Vec<String>, bool>>,
+}
+
+impl<F: Fn(&[String]) -> bool + Sync> CachedTest<F> {
+    fn new(test: F) -> Self {
+        CachedTest {
+            test,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn reproduces(&self, flags: &[String]) -> bool {
+        if let Some(result) = self.cache.lock().unwrap().get(flags) {
+            return *result;
+        }
+        let result = (self.test)(flags);
+        self.cache.lock().unwrap().insert(flags.to_vec(), result);
+        result
+    }
+
+    /// the first candidate (in order) that reproduces, tested in parallel
+    fn first_reproducing(&self, candidates: Vec<Vec<String>>) -> Option<Vec<String>> {
+        let position = candidates
+            .par_iter()
+            .position_first(|candidate| self.reproduces(candidate))?;
+        candidates.into_iter().nth(position)
+    }
+}
+
+/// split flags into n chunks of about the same size
+fn chunks(flags: &[String], n: usize) -> Vec<Vec<String>> {
+    let chunk_size = flags.len().div_ceil(n);
+    flags.chunks(chunk_size).map(<[String]>::to_vec).collect()
+}
+
+fn ddmin<F: Fn(&[String]) -> bool + Sync>(flags: Vec<String>, test: &CachedTest<F>) -> Vec<String> {
+    if test.reproduces(&[]) {
+        return Vec::new();
+    }
+
+    let mut flags = flags;
+    let mut n = 2;
+    while flags.len() >= 2 {
+        let subsets = chunks(&flags, n);
+        let complements = (0..subsets.len())
+            .map(|skip| {
+                subsets
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != skip)
+                    .flat_map(|(_, subset)| subset.iter().cloned())
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(subset) = test.first_reproducing(subsets.clone()) {
+            flags = subset;
+            n = 2;
+        } else if let Some(complement) = test.first_reproducing(complements) {
+            flags = complement;
+            n = (n - 1).max(2);
+        } else if n < flags.len() {
+            n = (n * 2).min(flags.len());
+        } else {
+            break;
+        }
+    }
+
+    // ddmin never tests removing the last flag on its own
+    if flags.len() == 1 && test.reproduces(&[]) {
+        flags.clear();
+    }
+    flags
+}
+
+/// "-Zmir-opt-level=4" => ("-Zmir-opt-level=", 4)
+fn numeric_value(flag: &str) -> Option<(&str, u32)> {
+    let (name, value) = flag.rsplit_once('=')?;
+    let value = value.parse::<u32>().ok()?;
+    Some((&flag[..=name.len()], value))
+}
+
+/// lower numeric values such as -Zmir-opt-level=4 as far as possible
+fn minimize_values<F: Fn(&[String]) -> bool + Sync>(
+    mut flags: Vec<String>,
+    test: &CachedTest<F>,
+) -> Vec<String> {
+    for i in 0..flags.len() {
+        let Some((prefix, value)) = numeric_value(&flags[i]) else {
+            continue;
+        };
+        if value == 0 || value > MAX_MINIMIZED_VALUE {
+            continue;
+        }
+        let prefix = prefix.to_string();
+        let candidates = (0..value)
+            .map(|lower| {
+                let mut candidate = flags.clone();
+                candidate[i] = format!("{prefix}{lower}");
+                candidate
+            })
+            .collect::<Vec<_>>();
+        if let Some(lower) = test.first_reproducing(candidates) {
+            flags = lower;
+        }
+    }
+    flags
+}
+
+/// the smallest subset of `flags` with the lowest values for which `reproduces` still holds
+pub(crate) fn minimize_flags(
+    flags: Vec<String>,
+    reproduces: impl Fn(&[String]) -> bool + Sync,
+) -> Vec<String> {
+    let test = CachedTest::new(reproduces);
+    let minimized = ddmin(flags, &test);
+    minimize_values(minimized, &test)
+}
+
+/// move -Zcrate-attr=feature(..) flags into the code as #![feature(..)] attributes,
+/// returns the new code and the remaining flags
+pub(crate) fn merge_feature_flags(code: &str, flags: &[String]) -> (String, Vec<String>) {
+    let (features, remaining_flags): (Vec<&String>, Vec<&String>) = flags
+        .iter()
+        .partition(|flag| flag.starts_with("-Zcrate-attr=feature("));
+    if features.is_empty() {
+        return (code.to_string(), flags.to_vec());
+    }
+
+    // enabling a feature twice is an error
+    let already_enabled = enabled_features(code);
+    let attributes = features
+        .iter()
+        .map(|flag| flag.trim_start_matches("-Zcrate-attr="))
+        .filter(|feature| {
+            let name = feature
+                .trim_start_matches("feature(")
+                .trim_end_matches(')')
+                .trim();
+            !already_enabled.iter().any(|enabled| enabled == name)
+        })
+        .map(|feature| format!("#![{feature}]\n"))
+        .collect::<String>();
+
+    (
+        format!("{attributes}{code}"),
+        remaining_flags.into_iter().cloned().collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(flags: &[&str]) -> Vec<String> {
+        flags.iter().map(|flag| flag.to_string()).collect()
+    }
+
+    #[test]
+    fn ddmin_finds_minimal_subset() {
+        let all = flags(&[
+            "-Za", "-Zb", "-Zc", "-Zd", "-Ze", "-Zf", "-Zg", "-Zh", "-Zi",
+        ]);
+        let minimized = minimize_flags(all, |flags| {
+            flags.contains(&String::from("-Zc")) && flags.contains(&String::from("-Zh"))
+        });
+        assert_eq!(minimized, flags(&["-Zc", "-Zh"]));
+    }
+
+    #[test]
+    fn lowest_value() {
+        let minimized = minimize_flags(
+            flags(&[
+                "-Zmir-opt-level=4",
+                "--crate-type=lib",
+                "-Zinline-mir-threshold=1000",
+            ]),
+            |flags| {
+                flags.iter().any(|flag| {
+                    numeric_value(flag)
+                        .is_some_and(|(prefix, level)| prefix == "-Zmir-opt-level=" && level >= 2)
+                })
+            },
+        );
+        assert_eq!(minimized, flags(&["-Zmir-opt-level=2"]));
+    }
+
+    #[test]
+    fn features_into_code() {
+        let (code, remaining) = merge_feature_flags(
+            "fn main() {}\n",
+            &flags(&[
+                "-Zcrate-attr=feature(generic_const_exprs)",
+                "-Zmir-opt-level=3",
+            ]),
+        );
+        assert_eq!(code, "#![feature(generic_const_exprs)]\nfn main() {}\n");
+        assert_eq!(remaining, flags(&["-Zmir-opt-level=3"]));
+
+        let (code, _) = merge_feature_flags(
+            "#![feature(generic_const_exprs)]\n",
+            &flags(&["-Zcrate-attr=feature(generic_const_exprs)"]),
+        );
+        assert_eq!(code, "#![feature(generic_const_exprs)]\n");
+    }
+}
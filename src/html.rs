@@ -0,0 +1,345 @@
+// render the findings of one or more projects into a static, browsable site
+//
+// icemaker_html/index.html           overview of all findings, grouped by fingerprint
+// icemaker_html/findings/<id>.html   one page per finding
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tree_sitter::{Node, Parser};
+
+use crate::ice::{read_errors_json, ICE, REPORTS_DIR};
+use crate::reduce::{cached_reduction, REDUCTION_DIR};
+
+pub(crate) const HTML_DIR: &str = "icemaker_html";
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+td, th { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; vertical-align: top; }
+tr.group td { background: #eee; font-weight: bold; }
+pre { background: #f6f8fa; padding: 1em; overflow-x: auto; }
+.kw { color: #a626a4; font-weight: bold; }
+.ty { color: #c18401; }
+.st { color: #50a14f; }
+.nu { color: #986801; }
+.cm { color: #a0a1a7; font-style: italic; }
+.at { color: #4078f2; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// css class for a tree-sitter node, None if it should not be highlighted
+fn highlight_class(node: &Node<'_>) -> Option<&'static str> {
+    match node.kind() {
+        "line_comment" | "block_comment" => Some("cm"),
+        "string_literal" | "raw_string_literal" | "char_literal" => Some("st"),
+        "integer_literal" | "float_literal" | "boolean_literal" => Some("nu"),
+        "primitive_type" | "type_identifier" => Some("ty"),
+        "attribute_item" | "inner_attribute_item" => Some("at"),
+        // anonymous leaf nodes such as "fn", "let", "impl"
+        kind if !node.is_named() && kind.chars().all(|c| c.is_ascii_lowercase() || c == '_') => {
+            Some("kw")
+        }
+        _ => None,
+    }
+}
+
+fn collect_highlights(node: Node<'_>, spans: &mut Vec<(usize, usize, &'static str)>) {
+    if let Some(class) = highlight_class(&node) {
+        // do not descend into strings, comments or attributes
+        spans.push((node.start_byte(), node.end_byte(), class));
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_highlights(child, spans);
+    }
+}
+
+/// html-escaped rust code with <span>s for keywords, types, literals, comments and attributes
+pub(crate) fn highlight_rust(code: &str) -> String {
+    let mut parser = Parser::new();
+    parser.set_timeout_micros(10_000_000);
+    if parser.set_language(&tree_sitter_rust::language()).is_err() {
+        return escape(code);
+    }
+    let Some(tree) = parser.parse(code, None) else {
+        return escape(code);
+    };
+
+    let mut spans = Vec::new();
+    collect_highlights(tree.root_node(), &mut spans);
+
+    let mut html = String::new();
+    let mut pos = 0;
+    for (start, end, class) in spans {
+        if start < pos {
+            continue;
+        }
+        html.push_str(&escape(&code[pos..start]));
+        html.push_str(&format!(
+            "<span class=\"{class}\">{}</span>",
+            escape(&code[start..end])
+        ));
+        pos = end;
+    }
+    html.push_str(&escape(&code[pos..]));
+    html
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{}</title>
+<style>{STYLE}</style>
+</head>
+<body>
+{body}
+</body>
+</html>
+",
+        escape(title)
+    )
+}
+
+/// stable name of the page of a finding
+fn finding_id(ice: &ICE) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(ice.fingerprint());
+    hasher.update(ice.file.display().to_string());
+    hasher.update(ice.args.join(" "));
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..16].to_string()
+}
+
+/// the most recent report that was written for this finding, if any
+fn find_report(ice: &ICE) -> Option<PathBuf> {
+    // the REPORTS_DIR of every run is a dated dir next to ours
+    let reports_root = REPORTS_DIR.parent()?;
+    let mut runs = std::fs::read_dir(reports_root)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect::<Vec<PathBuf>>();
+    // report dirs are named by date, newest last
+    runs.sort();
+    runs.into_iter()
+        .rev()
+        .map(|run| run.join(ice.report_file_name()))
+        .find(|report| report.is_file())
+}
+
+fn code_block(title: &str, code: Option<String>) -> String {
+    match code {
+        Some(code) => format!("<h2>{title}</h2>\n<pre>{}</pre>\n", highlight_rust(&code)),
+        None => format!("<h2>{title}</h2>\n<p>not available</p>\n"),
+    }
+}
+
+fn render_finding(ice: &ICE, project: &Path) -> String {
+    let original_path = project.join(&ice.file);
+    let original = std::fs::read_to_string(&original_path).ok();
+    let reduction = [project.join(REDUCTION_DIR), PathBuf::from(REDUCTION_DIR)]
+        .iter()
+        .find_map(|dir| cached_reduction(ice, &original_path, dir));
+    let ice_on_valid = ice.ice_on_valid
+        || reduction
+            .as_ref()
+            .is_some_and(|reduction| reduction.is_ice_on_valid_code());
+    let valid_mvce = reduction
+        .as_ref()
+        .and_then(|reduction| reduction.valid_mvce.clone());
+    let mvce = reduction.map(|reduction| reduction.mvce);
+
+    let mut stderr = format!("{}\n{}\n", ice.ice_msg, ice.error_reason);
+    if !ice.query_stack.is_empty() {
+        stderr.push('\n');
+        stderr.push_str(&ice.query_stack.join("\n"));
+    }
+
+    let report = match find_report(ice) {
+        Some(report) => format!(
+            "<a href=\"file://{}\">{}</a>",
+            escape(&report.display().to_string()),
+            escape(&report.display().to_string())
+        ),
+        None => String::from("no report generated yet"),
+    };
+
+    let body = format!(
+        "<p><a href=\"../index.html\">back to overview</a></p>
+<h1>{kind:?} in {executable:?}</h1>
+<table>
+<tr><th>file</th><td>{file}</td></tr>
+<tr><th>executable</th><td>{executable:?}</td></tr>
+<tr><th>kind</th><td>{kind:?}</td></tr>
+<tr><th>regresses on</th><td>{regresses_on}</td></tr>
+<tr><th>uses features</th><td>{needs_feature}</td></tr>
+<tr><th>ICE on valid code</th><td>{ice_on_valid}</td></tr>
+<tr><th>reproduces on editions</th><td>{editions}</td></tr>
+<tr><th>regression window</th><td>{regression_window}</td></tr>
+<tr><th>toolchains</th><td>{toolchains}</td></tr>
+<tr><th>toolchain diff</th><td>{toolchain_diff}</td></tr>
+<tr><th>debug assertions</th><td>{debug_assertions}</td></tr>
+<tr><th>flags</th><td><code>{flags}</code></td></tr>
+<tr><th>fingerprint</th><td><code>{fingerprint}</code></td></tr>
+<tr><th>report</th><td>{report}</td></tr>
+</table>
+<h2>stderr excerpt</h2>
+<pre>{stderr}</pre>
+{mvce}{valid_mvce}{original}",
+        kind = ice.kind,
+        executable = ice.executable,
+        file = escape(&original_path.display().to_string()),
+        regresses_on = ice.regresses_on,
+        needs_feature = ice.needs_feature,
+        toolchains = match &ice.regression_detailed {
+            Some(matrix) => matrix
+                .rows()
+                .iter()
+                .map(|(toolchain, outcome)| format!("{toolchain}: {outcome}"))
+                .collect::<Vec<String>>()
+                .join("<br>"),
+            None => String::from("not checked"),
+        },
+        regression_window = match &ice.regression_window {
+            Some(window) => match window.compare_url() {
+                Some(url) => format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape(&url),
+                    escape(&window.to_string())
+                ),
+                None => escape(&window.to_string()),
+            },
+            None => String::from("not bisected"),
+        },
+        debug_assertions = match ice.debug_assertions {
+            Some(sensitivity) => format!("crashes {sensitivity}"),
+            None => String::from("not checked"),
+        },
+        toolchain_diff = match &ice.toolchain_diff {
+            Some(diff) => escape(&diff.to_string()),
+            None => String::from("not diffed"),
+        },
+        editions = match ice.editions.as_slice() {
+            [] => String::from("not checked"),
+            editions => editions.join(", "),
+        },
+        flags = escape(&ice.args.join(" ")),
+        fingerprint = escape(&ice.fingerprint()),
+        stderr = escape(&stderr),
+        mvce = code_block("MVCE", mvce),
+        valid_mvce = code_block("MVCE that compiles without flags", valid_mvce),
+        original = code_block("original file", original),
+    );
+
+    let title = format!("{:?} {}", ice.executable, ice.file.display());
+    page(&title, &body)
+}
+
+fn count_table(title: &str, keys: impl Iterator<Item = String>) -> String {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    keys.for_each(|key| *counts.entry(key).or_default() += 1);
+
+    let rows = counts
+        .iter()
+        .map(|(key, count)| format!("<tr><td>{}</td><td>{count}</td></tr>\n", escape(key)))
+        .collect::<String>();
+    format!("<h2>by {title}</h2>\n<table>\n<tr><th>{title}</th><th>findings</th></tr>\n{rows}</table>\n")
+}
+
+/// render the errors.json of all projects into HTML_DIR
+pub(crate) fn render_html(projects: &[PathBuf]) {
+    let out_dir = PathBuf::from(HTML_DIR);
+    let findings_dir = out_dir.join("findings");
+    std::fs::create_dir_all(&findings_dir).expect("failed to create icemaker_html dir");
+
+    let findings = projects
+        .iter()
+        .flat_map(|project| {
+            read_errors_json(&project.join("errors.json"))
+                .into_iter()
+                .map(move |ice| (ice, project.clone()))
+        })
+        .collect::<Vec<(ICE, PathBuf)>>();
+
+    // fingerprint => findings
+    let mut groups: BTreeMap<String, Vec<&(ICE, PathBuf)>> = BTreeMap::new();
+    findings.iter().for_each(|finding| {
+        groups
+            .entry(finding.0.fingerprint())
+            .or_default()
+            .push(finding)
+    });
+
+    let mut overview = String::new();
+    for (fingerprint, group) in &groups {
+        overview.push_str(&format!(
+            "<tr class=\"group\"><td colspan=\"5\">{} ({} findings)</td></tr>\n",
+            escape(fingerprint),
+            group.len()
+        ));
+        for (ice, project) in group {
+            let id = finding_id(ice);
+            std::fs::write(
+                findings_dir.join(format!("{id}.html")),
+                render_finding(ice, project),
+            )
+            .expect("failed to write finding page");
+
+            overview.push_str(&format!(
+                "<tr><td><a href=\"findings/{id}.html\">{file}</a></td><td>{kind:?}</td><td>{executable:?}</td><td>{regresses_on}</td><td><code>{flags}</code></td></tr>\n",
+                file = escape(&ice.file.display().to_string()),
+                kind = ice.kind,
+                executable = ice.executable,
+                regresses_on = ice.regresses_on,
+                flags = escape(&ice.args.join(" ")),
+            ));
+        }
+    }
+
+    let body = format!(
+        "<h1>icemaker findings</h1>
+<p>{findings} findings, {groups} fingerprints, generated {date}</p>
+{by_kind}{by_executable}{by_channel}<h2>by fingerprint</h2>
+<table>
+<tr><th>file</th><th>kind</th><th>executable</th><th>regresses on</th><th>flags</th></tr>
+{overview}</table>",
+        findings = findings.len(),
+        groups = groups.len(),
+        date = chrono::offset::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        by_kind = count_table(
+            "kind",
+            findings.iter().map(|(ice, _)| format!("{:?}", ice.kind))
+        ),
+        by_executable = count_table(
+            "executable",
+            findings
+                .iter()
+                .map(|(ice, _)| format!("{:?}", ice.executable))
+        ),
+        by_channel = count_table(
+            "regression channel",
+            findings.iter().map(|(ice, _)| ice.regresses_on.to_string())
+        ),
+    );
+
+    std::fs::write(out_dir.join("index.html"), page("icemaker findings", &body))
+        .expect("failed to write index.html");
+
+    println!(
+        "wrote {} findings to {}",
+        findings.len(),
+        out_dir.join("index.html").display()
+    );
+}
@@ -1,17 +1,31 @@
-use std::{io::Write, path::PathBuf};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
 use colored::Colorize;
 use lazy_static::lazy_static;
 use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tempdir::TempDir;
 
-use crate::{library::Args, reduce_ice_code_to_string, run_commands::prlimit_run_command};
+use crate::{
+    bisect::RegressionWindow,
+    debug_assertions::AssertionSensitivity,
+    duplicates::ISSUE_DUMP,
+    editions::with_edition,
+    flag_minimizer::merge_feature_flags,
+    library::Args,
+    reduce::{reduce_ice_code, Reduction},
+    run_commands::prlimit_run_command,
+    toolchain_diff::ToolchainDiff,
+};
 
 // represents a crash that we found by running an `Executable` with a set of flags on a .rs file
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ICE {
     // what release channel did we crash on?
     pub regresses_on: Regression,
@@ -34,8 +48,54 @@ pub struct ICE {
     pub query_stack: Vec<String>,
     // what kind of ice is this?
     pub kind: ICEKind,
+    // suggested labels for the issue tracker, see triage.rs
+    #[serde(default)]
+    pub labels: Vec<String>,
+    // compiler crate that the panic came from
+    #[serde(default)]
+    pub component: Option<String>,
+    // the reduced code still compiles fine without the flags that make it crash
+    #[serde(default)]
+    pub ice_on_valid: bool,
+    // editions the finding reproduces on, oldest first. empty if we did not check
+    #[serde(default)]
+    pub editions: Vec<String>,
+    // nightlies between which the finding was introduced, see --bisect
+    #[serde(default)]
+    pub regression_window: Option<RegressionWindow>,
+    // result of the finding on every toolchain, regresses_on is derived from it
+    #[serde(default)]
+    pub regression_detailed: Option<RegressionDetailed>,
+    // appeared or disappeared between two toolchains, see --diff-toolchains
+    #[serde(default)]
+    pub toolchain_diff: Option<ToolchainDiff>,
+    // does the crash need debug assertions? None if we could not run both builds
+    #[serde(default)]
+    pub debug_assertions: Option<AssertionSensitivity>,
+    // other files with the same fingerprint, found by another shard, see --merge
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_files: Vec<PathBuf>,
+}
+
+// labels, component, ice_on_valid, editions, regression_window, regression_detailed,
+// toolchain_diff, debug_assertions and duplicate_files are derived from the other fields, an
+// errors.json from before they were added should not make every finding look new
+impl PartialEq for ICE {
+    fn eq(&self, other: &Self) -> bool {
+        self.regresses_on == other.regresses_on
+            && self.needs_feature == other.needs_feature
+            && self.file == other.file
+            && self.args == other.args
+            && self.error_reason == other.error_reason
+            && self.ice_msg == other.ice_msg
+            && self.executable == other.executable
+            && self.query_stack == other.query_stack
+            && self.kind == other.kind
+    }
 }
 
+impl Eq for ICE {}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
 pub enum ICEKind {
@@ -54,6 +114,25 @@ pub enum ICEKind {
     DoubleIce,
     // rustfmt failed to format the code
     RustfmtFailure,
+    // compiles on stable but not on master or the other way around, without any crash
+    AcceptReject(AcceptReject),
+}
+
+impl ICEKind {
+    /// name of the variant, without any of its data
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ICEKind::Ice(_) => "Ice",
+            ICEKind::Ub(_) => "Ub",
+            ICEKind::Hang(_) => "Hang",
+            ICEKind::OOM => "OOM",
+            ICEKind::RustFix => "RustFix",
+            ICEKind::TypeError => "TypeError",
+            ICEKind::DoubleIce => "DoubleIce",
+            ICEKind::RustfmtFailure => "RustfmtFailure",
+            ICEKind::AcceptReject(_) => "AcceptReject",
+        }
+    }
 }
 
 impl Default for ICEKind {
@@ -62,6 +141,23 @@ impl Default for ICEKind {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+pub enum AcceptanceChange {
+    // compiles on stable, but not on master
+    NewlyRejected,
+    // compiles on master, but not on stable
+    NewlyAccepted,
+}
+
+// see --accept-reject
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct AcceptReject {
+    pub change: AcceptanceChange,
+    // errors of the toolchain that rejects the code, empty for the one that accepts it
+    pub stable_diagnostics: String,
+    pub master_diagnostics: String,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
 pub enum Interestingness {
     VeryInteresting,
@@ -70,13 +166,187 @@ pub enum Interestingness {
     Boring,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone, Eq, PartialEq)]
-pub enum UbKind {
+// what kind of undefined behaviour did miri find?
+#[derive(
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Hash,
+    PartialOrd,
+    Ord,
+    clap::ValueEnum,
+)]
+pub enum UbCategory {
+    // stacked borrows / tree borrows violation
+    Aliasing,
+    Uninit,
+    OutOfBounds,
+    Misalignment,
+    InvalidEnumDiscriminant,
+    DanglingPointer,
+    DataRace,
+    Leak,
+    // miri does not support what the program does
+    Unsupported,
     #[default]
+    Other,
+}
+
+impl std::fmt::Display for UbCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let s = match self {
+            UbCategory::Aliasing => "aliasing violation",
+            UbCategory::Uninit => "use of uninitialized memory",
+            UbCategory::OutOfBounds => "out-of-bounds access",
+            UbCategory::Misalignment => "misaligned access",
+            UbCategory::InvalidEnumDiscriminant => "invalid enum discriminant",
+            UbCategory::DanglingPointer => "dangling pointer",
+            UbCategory::DataRace => "data race",
+            UbCategory::Leak => "memory leak",
+            UbCategory::Unsupported => "unsupported operation",
+            UbCategory::Other => "other",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+// the first matching pattern wins, so more specific ones go first
+static UB_CATEGORY_PATTERNS: Lazy<Vec<(UbCategory, Regex)>> = Lazy::new(|| {
+    [
+        (UbCategory::DataRace, "(?i)data race"),
+        (
+            UbCategory::Aliasing,
+            "(?i)stacked borrows|tree borrows|borrow stack|tag does not exist|is not granting access|protector",
+        ),
+        (UbCategory::Uninit, "(?i)uninitialized"),
+        (UbCategory::InvalidEnumDiscriminant, "(?i)enum discriminant|enum value has invalid tag|invalid tag"),
+        (
+            UbCategory::DanglingPointer,
+            "(?i)dangling|use-after-free|has been freed|got freed|no provenance",
+        ),
+        (UbCategory::OutOfBounds, "(?i)out-of-bounds|out of bounds|outside the bounds"),
+        // allocation and layout errors mention the alignment as well
+        (
+            UbCategory::Misalignment,
+            r"(?i)misaligned pointer|alignment \d+, but alignment \d+ is required|unaligned (reference|box)",
+        ),
+        (UbCategory::Leak, "(?i)leaked memory|memory leaked"),
+        (UbCategory::Unsupported, "(?i)unsupported operation"),
+    ]
+    .into_iter()
+    .map(|(category, re)| (category, Regex::new(re).unwrap()))
+    .collect()
+});
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(from = "UbKindRepr")]
+pub struct UbKind {
+    pub category: UbCategory,
+    // ub in a program without "unsafe" probably means that miri or rustc is broken
+    pub interesting: bool,
+    // --> src/main.rs:6:14
+    pub span: Option<String>,
+    // the "help: " lines that miri attached to the error
+    pub help: Vec<String>,
+}
+
+// UbKind used to be an enum, keep reading the errors.json files written back then
+#[derive(Deserialize)]
+enum LegacyUbKind {
     Interesting,
     Uninteresting,
 }
 
+#[derive(Deserialize)]
+struct UbKindFields {
+    category: UbCategory,
+    interesting: bool,
+    span: Option<String>,
+    help: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UbKindRepr {
+    Fields(UbKindFields),
+    Legacy(LegacyUbKind),
+}
+
+impl From<UbKindRepr> for UbKind {
+    fn from(repr: UbKindRepr) -> Self {
+        match repr {
+            UbKindRepr::Fields(UbKindFields {
+                category,
+                interesting,
+                span,
+                help,
+            }) => UbKind {
+                category,
+                interesting,
+                span,
+                help,
+            },
+            UbKindRepr::Legacy(legacy) => UbKind {
+                interesting: matches!(legacy, LegacyUbKind::Interesting),
+                ..UbKind::default()
+            },
+        }
+    }
+}
+
+impl UbKind {
+    /// classify the miri output that follows (and includes) the line where we found the UB
+    pub(crate) fn from_miri_output(ub_line: &str, output: &str) -> Self {
+        let category = UB_CATEGORY_PATTERNS
+            .iter()
+            .find(|(_, re)| re.is_match(ub_line))
+            .map(|(category, _)| *category)
+            .unwrap_or_default();
+
+        // everything that belongs to this error, up to the next one
+        let error_lines = output
+            .lines()
+            .skip_while(|line| !line.contains(ub_line))
+            .skip(1)
+            .take_while(|line| !line.starts_with("error"))
+            .map(str::trim)
+            .collect::<Vec<&str>>();
+
+        let span = error_lines
+            .iter()
+            .find_map(|line| line.strip_prefix("--> "))
+            .map(str::to_string);
+
+        let help = error_lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("= help: "))
+            .map(str::to_string)
+            .collect();
+
+        UbKind {
+            category,
+            interesting: false,
+            span,
+            help,
+        }
+    }
+}
+
+static MIRI_SUPPRESSED_UB: Lazy<Vec<UbCategory>> = Lazy::new(|| Args::parse().miri_suppress);
+
+impl UbCategory {
+    /// categories passed via --miri-suppress are not reported at all
+    pub(crate) fn is_suppressed(&self) -> bool {
+        MIRI_SUPPRESSED_UB.contains(self)
+    }
+}
+
 // is this actually used?
 impl std::fmt::Display for ICE {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
@@ -100,6 +370,127 @@ impl std::fmt::Display for ICE {
 
 pub(crate) type ICEDisplay = String;
 
+/// read the ICEs of a previous run from an errors.json
+/// if the file does not exist or can not be parsed, start blank
+pub(crate) fn read_errors_json(errors_json: &Path) -> Vec<ICE> {
+    if !errors_json.exists() {
+        // we don't have a file, start blank
+        return Vec::new();
+    }
+    let read = match std::fs::read_to_string(errors_json) {
+        Ok(content) => content,
+        Err(_) => panic!("failed to read '{}'", errors_json.display()),
+    };
+    match serde_json::from_str(&read) {
+        Ok(previous_errors) => previous_errors,
+        Err(e) => {
+            // this can happen if we for example change the representation of Ice so that that the previous file is no longer compatible with the new format
+            eprintln!("Failed to parse errors.json, is it a json file?");
+            eprintln!("original error: '{e:?}'");
+            Vec::new()
+        }
+    }
+}
+
+// compiler/rustc_hir_typeck/src/expr.rs:123:45
+static PANIC_LOCATION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(compiler|library|src/tools|src/librustdoc)/[A-Za-z0-9_./-]+\.rs:\d+:\d+").unwrap()
+});
+
+// #3 [typeck] type-checking `main`
+static QUERY_FRAME_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#\d+ \[(\w+)\]").unwrap());
+
+/// find the first path:line:col inside the compiler (or std, or a tool) in a text
+pub(crate) fn panic_location_from_str(text: &str) -> Option<String> {
+    PANIC_LOCATION_REGEX
+        .find(text)
+        .map(|location| location.as_str().to_string())
+}
+
+/// strip everything from an ice message that changes between otherwise identical crashes:
+/// locations, numbers, DefIds, hex addresses, quoting and the "thread panicked at" boilerplate
+pub(crate) fn normalize_ice_msg(msg: &str) -> String {
+    static NOISE: Lazy<Vec<Regex>> = Lazy::new(|| {
+        [
+            r"thread '[^']*' panicked at",
+            r"error: internal compiler error:",
+            r"ICE:",
+            r"DefId\([^)]*\)",
+            r"0x[0-9a-fA-F]+",
+        ]
+        .into_iter()
+        .map(|re| Regex::new(re).unwrap())
+        .collect()
+    });
+    static NUMBERS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
+
+    let msg = PANIC_LOCATION_REGEX.replace_all(msg, " ");
+    let msg = NOISE.iter().fold(msg.to_string(), |msg, re| {
+        re.replace_all(&msg, " ").to_string()
+    });
+    let msg = NUMBERS.replace_all(&msg, "N");
+
+    msg.replace(['\'', '"', '`', ':', ','], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// the query names of a query stack: `#0 [typeck] type-checking `main`` => `typeck`
+pub(crate) fn query_frames_from_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+    lines
+        .filter_map(|line| QUERY_FRAME_REGEX.captures(line.trim()))
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+impl ICE {
+    pub(crate) fn panic_location(&self) -> Option<String> {
+        panic_location_from_str(&self.ice_msg)
+            .or_else(|| panic_location_from_str(&self.error_reason))
+    }
+
+    pub(crate) fn normalized_msg(&self) -> String {
+        normalize_ice_msg(&self.ice_msg)
+    }
+
+    pub(crate) fn query_frames(&self) -> Vec<String> {
+        query_frames_from_lines(self.query_stack.iter().map(String::as_str))
+    }
+
+    /// identifies "the same bug" across files and flags
+    pub(crate) fn fingerprint(&self) -> String {
+        format!(
+            "{:?} {} {}",
+            self.executable,
+            self.panic_location().unwrap_or_default(),
+            self.normalized_msg()
+        )
+    }
+
+    /// name of the markdown file inside the REPORTS_DIR that `Report::to_disk` writes
+    pub(crate) fn report_file_name(&self) -> String {
+        // should just print Rustc, Miri, Clippy etc...
+        // we need to append this so that if the miri and rustdoc crash on the file, we don't overwrite previous results :/
+        let executable = format!("{:?}", self.executable);
+
+        let display = self
+            .file
+            .file_name()
+            .unwrap_or_default()
+            .to_str()
+            .unwrap_or_default()
+            .to_string();
+        let mut file_on_disk = display.to_string().replace(['/', '\\'], "_");
+        file_on_disk.push('_');
+        file_on_disk.push_str(&executable);
+        let mut file_on_disk = file_on_disk.replace(".rs", "");
+        file_on_disk.push_str(".md");
+        file_on_disk
+    }
+}
+
 impl ICE {
     // print a ICE to stdout or something
     pub(crate) fn to_printable(&self) -> ICEDisplay {
@@ -107,14 +498,26 @@ impl ICE {
             ICEKind::Ice(Interestingness::VeryInteresting) => "ICE!".red(),
             ICEKind::Ice(Interestingness::Interesting) => "ICE".red(),
             ICEKind::Ice(Interestingness::Boring) => "ice".normal(),
-            ICEKind::Ub(UbKind::Interesting) => "UB".green(),
-            ICEKind::Ub(UbKind::Uninteresting) => "ub".normal(),
+            ICEKind::Ub(UbKind {
+                interesting: true, ..
+            }) => "UB".green(),
+            ICEKind::Ub(UbKind {
+                interesting: false, ..
+            }) => "ub".normal(),
             ICEKind::Hang(_) => "HANG".blue(),
             ICEKind::OOM => "OOM".red(),
             ICEKind::RustFix => "RustFix".yellow(),
             ICEKind::TypeError => "TypeError".yellow(),
             ICEKind::DoubleIce => "DoubleICE".red(),
             ICEKind::RustfmtFailure => "Fmt".yellow(),
+            ICEKind::AcceptReject(AcceptReject {
+                change: AcceptanceChange::NewlyRejected,
+                ..
+            }) => "REJECTED".magenta(),
+            ICEKind::AcceptReject(AcceptReject {
+                change: AcceptanceChange::NewlyAccepted,
+                ..
+            }) => "accepted".normal(),
         };
 
         let flags = self.args.join(" ");
@@ -126,8 +529,13 @@ impl ICE {
         // let report: Report = self.clone().into_report(global_tempdir);
         //  report.to_disk();
 
+        let ub_category = match &self.kind {
+            ICEKind::Ub(ub) => format!(" ({})", ub.category),
+            _ => String::new(),
+        };
+
         format!(
-            "{kind}: {:?} {} '{flags}' '{}', '{}'",
+            "{kind}{ub_category}: {:?} {} '{flags}' '{}', '{}'",
             self.executable,
             self.file.display(),
             self.ice_msg.normal(),
@@ -158,7 +566,60 @@ impl ICE {
         let ice = &self;
 
         let ice_oneliner = self.to_printable();
-        let mvce_string: String = reduce_ice_code_to_string(ice.clone(), global_tempdir_path);
+        let possible_duplicate = ISSUE_DUMP
+            .as_ref()
+            .and_then(|dump| dump.best_match(ice))
+            .map(|dup| format!("\n{dup}"))
+            .unwrap_or_default();
+        let labels = match ice.triage_line() {
+            line if line.is_empty() => String::new(),
+            line => format!("\nLabels: {line}"),
+        };
+        // reports are written while the corpus is checked, one oracle at a time is enough
+        let reduction = reduce_ice_code(ice, global_tempdir_path, 1);
+        let ice_on_valid = reduction
+            .as_ref()
+            .is_some_and(Reduction::is_ice_on_valid_code);
+        let valid_mvce = reduction
+            .as_ref()
+            .and_then(|reduction| reduction.valid_mvce.clone());
+        let mvce_string: String = reduction
+            .map(|reduction| reduction.mvce)
+            .unwrap_or_else(|| String::from("ERROR in icemaker while reducing file"));
+        let editions_line = if ice.editions.is_empty() {
+            String::new()
+        } else {
+            format!("\nreproduces on editions {}", ice.editions.join(", "))
+        };
+        let regression_line = match &ice.regression_window {
+            Some(window) => format!(
+                "\nregression window: {window}{}",
+                window
+                    .compare_url()
+                    .map(|url| format!("\n{url}"))
+                    .unwrap_or_default()
+            ),
+            None => String::new(),
+        };
+        let debug_assertions_line = ice
+            .debug_assertions
+            .map(|sensitivity| format!("\ncrashes {sensitivity}"))
+            .unwrap_or_default();
+        let toolchain_diff_line = ice
+            .toolchain_diff
+            .as_ref()
+            .map(|diff| format!("\n{diff}"))
+            .unwrap_or_default();
+        let toolchains = ice
+            .regression_detailed
+            .as_ref()
+            .map(|matrix| format!("\n{}", matrix.to_markdown()))
+            .unwrap_or_default();
+        let ice_on_valid_line = if ice_on_valid {
+            "\nICE on valid code"
+        } else {
+            ""
+        };
         //dbg!(&mvce_string);
 
         let tempdir =
@@ -170,6 +631,20 @@ impl ICE {
         let original_path_display = original_path.display();
         let original_code = std::fs::read_to_string(&original_path).unwrap_or("<error>".into());
 
+        // if we failed to reduce the originl code, don't print original and snippet
+        let reduction_failed = mvce_string == original_code;
+
+        // move the feature flags into the mvce as #![feature(x)]
+        let (mvce_string, args) = merge_feature_flags(&mvce_string, &ice.args);
+        // reproduce with the oldest edition that still crashes
+        let args = match ice.lowest_edition() {
+            Some(edition) => with_edition(&args, edition),
+            None => args,
+        };
+        let valid_mvce = valid_mvce
+            .filter(|valid_mvce| *valid_mvce != original_code)
+            .map(|valid_mvce| merge_feature_flags(&valid_mvce, &ice.args).0);
+
         // when fetching the Executable output, run Executable against the mvce inseatead of the original file
         // need to save the mvce to disk for this; put it into the tempdir
         let mvce_file_path = tempdir.path().join("mvce.rs");
@@ -179,19 +654,7 @@ impl ICE {
         write!(mvce_file, "{}", mvce_string)
             .expect(&format!("failed to write mvce '{mvce_display}'"));
 
-        // add feature flags into the mvce
-        // filter and convert ""-Zcrate-attr=feature(x)" to "#![feature(x)]"
-        let feature_flags = &ice
-            .args
-            .clone()
-            .iter()
-            .filter(|flag| flag.starts_with("-Zcrate-attr=feature("))
-            .map(|flag| flag.replace("-Zcrate-attr=", ""))
-            .map(|feature| format!("#![{feature}]\n"))
-            .collect::<String>();
-
-        let flags = ice
-            .args
+        let flags = args
             .clone()
             .into_iter()
             .filter(|flag| {
@@ -203,7 +666,7 @@ impl ICE {
         //let executable = &self.executable.clone();
         let executable_bin = &ice.executable.path();
         let mut cmd = std::process::Command::new(executable_bin);
-        cmd.args(&ice.args);
+        cmd.args(&args);
         cmd.arg(&mvce_file_path);
         cmd.current_dir(tempdir_path.to_string());
 
@@ -257,13 +720,11 @@ impl ICE {
             "<failed to get version>".to_string()
         };
 
-        // if we failed to reduce the originl code, don't print original and snippet
-        let snippet = if mvce_string == original_code {
+        let snippet = if reduction_failed {
             format!(
                 "snippet:
 ````rust
-{original_code}
-{feature_flags}
+{mvce_string}
 ````"
             )
         // if we have a very long original snippet. collapse it
@@ -272,7 +733,6 @@ impl ICE {
                 "auto-reduced (treereduce-rust):
 ````rust
 {mvce_string}
-{feature_flags}
 ````
 
 <details><summary><strong>original code</strong></summary>
@@ -290,7 +750,6 @@ original:
                 "auto-reduced (treereduce-rust):
 ````rust
 {mvce_string}
-{feature_flags}
 ````
 
 original:
@@ -300,13 +759,45 @@ original:
             )
         };
 
+        // the aggressive reduction may contain unrelated errors, this one does not
+        let valid_snippet = match valid_mvce {
+            Some(valid_mvce) if valid_mvce != mvce_string => format!(
+                "
+
+reduced to code that compiles without any flags (ICE on valid code):
+````rust
+{valid_mvce}
+````"
+            ),
+            _ => String::new(),
+        };
+
+        let ub_details = match &ice.kind {
+            ICEKind::Ub(ub) => format!(
+                "
+Undefined Behavior: {}{}
+{}
+",
+                ub.category,
+                ub.span
+                    .as_ref()
+                    .map(|span| format!(" at `{span}`"))
+                    .unwrap_or_default(),
+                ub.help
+                    .iter()
+                    .map(|help| format!("help: {help}\n"))
+                    .collect::<String>()
+            ),
+            _ => String::new(),
+        };
+
         let data = format!(
             "<!--
 {ice_oneliner}
-File: {original_path_display}
+File: {original_path_display}{possible_duplicate}{labels}{editions_line}{regression_line}{toolchain_diff_line}{debug_assertions_line}{ice_on_valid_line}
 -->
 
-{snippet}
+{snippet}{valid_snippet}
 
 Version information
 ````
@@ -315,7 +806,7 @@ Version information
 
 Command:
 `{executable_bin} {flags}`
-
+{toolchains}{ub_details}
 <!--
 Include a backtrace in the code block by setting `RUST_BACKTRACE=1` in your
 environment. E.g. `RUST_BACKTRACE=1 cargo build`.
@@ -339,7 +830,10 @@ query stack:
         );
 
         Report {
-            ice: ice.clone(),
+            ice: ICE {
+                ice_on_valid,
+                ..ice.clone()
+            },
             data,
         }
     }
@@ -374,30 +868,12 @@ impl Report {
             return;
         }
 
-        // should just print Rustc, Miri, Clippy etc...
-        // we need to append this so that if the miri and rustdoc crash on the file, we don't overwrite previous results :/
-        let executable = format!("{:?}", self.ice.executable);
-
         let reports_dir = REPORTS_DIR.to_owned();
         if !PathBuf::from(&reports_dir).exists() {
             std::fs::create_dir_all(&reports_dir).expect("failed to create icemaker reports dir!");
         }
 
-        let display = self
-            .ice
-            .file
-            .file_name()
-            .unwrap_or_default()
-            .to_str()
-            .unwrap_or_default()
-            .to_string();
-        let mut file_on_disk = display.to_string().replace(['/', '\\'], "_");
-        file_on_disk.push('_');
-        file_on_disk.push_str(&executable);
-        let mut file_on_disk = file_on_disk.replace(".rs", "");
-        file_on_disk.push_str(".md");
-
-        let report_file_path = reports_dir.join(file_on_disk);
+        let report_file_path = reports_dir.join(self.ice.report_file_name());
 
         dbg!(&report_file_path);
         eprintln!();
@@ -411,24 +887,104 @@ impl Report {
 }
 
 // in what channel a regression is first noticed?
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Eq, PartialEq)]
 pub enum Regression {
     Stable,
     Beta,
     Nightly,
+    #[default]
     Master,
 }
 
+/// what happens if we run a finding on one toolchain
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+pub enum ToolchainOutcome {
+    Ice,
+    CompileError,
+    Success,
+    // the toolchain does not know one of the flags
+    FlagsUnsupported,
+    NotInstalled,
+}
+
+impl std::fmt::Display for ToolchainOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let s = match self {
+            ToolchainOutcome::Ice => "ICE",
+            ToolchainOutcome::CompileError => "compile error",
+            ToolchainOutcome::Success => "success",
+            ToolchainOutcome::FlagsUnsupported => "flags unsupported",
+            ToolchainOutcome::NotInstalled => "not installed",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
 // ice is reproducible on:
-#[allow(non_snake_case, unused)]
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
 pub struct RegressionDetailed {
-    Stable: bool,
-    Beta: bool,
-    Nightly: bool,
-    Master: bool,
-    LocalDebugAssertions: bool,
+    // host triple of the stable, beta and nightly toolchains
+    pub host: String,
+    pub stable: ToolchainOutcome,
+    pub beta: ToolchainOutcome,
+    pub nightly: ToolchainOutcome,
+    pub master: ToolchainOutcome,
+    pub local_debug_assertions: ToolchainOutcome,
 }
 
+impl RegressionDetailed {
+    /// the oldest channel that crashes
+    pub(crate) fn regression(&self) -> Regression {
+        if self.stable == ToolchainOutcome::Ice {
+            Regression::Stable
+        } else if self.beta == ToolchainOutcome::Ice {
+            Regression::Beta
+        } else if self.nightly == ToolchainOutcome::Ice {
+            Regression::Nightly
+        } else {
+            Regression::Master
+        }
+    }
+
+    pub(crate) fn rows(&self) -> [(&'static str, ToolchainOutcome); 5] {
+        [
+            ("stable", self.stable),
+            ("beta", self.beta),
+            ("nightly", self.nightly),
+            ("master", self.master),
+            ("local-debug-assertions", self.local_debug_assertions),
+        ]
+    }
+
+    /// markdown table for the reports
+    pub(crate) fn to_markdown(&self) -> String {
+        let rows = self
+            .rows()
+            .iter()
+            .map(|(toolchain, outcome)| format!("| {toolchain} | {outcome} |\n"))
+            .collect::<String>();
+        format!(
+            "toolchains ({}):\n\n| toolchain | result |\n|---|---|\n{rows}",
+            self.host
+        )
+    }
+}
+
+/// host triple of the rustup installation, used to find the stable, beta and nightly toolchains
+pub(crate) static HOST_TRIPLE: Lazy<String> = Lazy::new(|| {
+    std::fs::read_to_string(RUSTUP_HOME.join("settings.toml"))
+        .ok()
+        .and_then(|settings| {
+            settings.lines().find_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                (key.trim() == "default_host_triple")
+                    .then(|| value.trim().trim_matches('"').to_string())
+            })
+        })
+        .unwrap_or_else(|| String::from("x86_64-unknown-linux-gnu"))
+});
+
 lazy_static! {
     pub(crate) static ref RUSTUP_HOME: PathBuf =
         home::rustup_home().expect("could not find rustup home");
@@ -447,8 +1003,9 @@ impl std::fmt::Display for Regression {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 pub enum Executable {
+    #[default]
     Rustc,
     Clippy,
     Rustdoc,
@@ -469,7 +1026,28 @@ pub enum Executable {
 
 static LOCAL_DEBUG_ASSERTIONS: Lazy<bool> = Lazy::new(|| Args::parse().local_debug_assertions);
 
+// exec path => `--version` output
+static VERSIONS: Lazy<std::sync::Mutex<std::collections::HashMap<String, String>>> =
+    Lazy::new(Default::default);
+
 impl Executable {
+    /// `--version` of the executable, only asked once per run
+    pub(crate) fn version(&self) -> String {
+        let path = self.path();
+        if let Some(version) = VERSIONS.lock().unwrap().get(&path) {
+            return version.clone();
+        }
+        let version = std::process::Command::new(&path)
+            .arg("--version")
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|version| !version.is_empty())
+            .unwrap_or_else(|| String::from("<failed to get version>"));
+        VERSIONS.lock().unwrap().insert(path, version.clone());
+        version
+    }
+
     pub fn path(&self) -> String {
         match self {
             Executable::Rustc => {
@@ -572,11 +1150,40 @@ impl Executable {
             Executable::Marker => String::from("marker"),
         }
     }
+
+    /// name of the binary inside the bin dir of a toolchain,
+    /// None if the tool can not be taken from a different toolchain
+    pub(crate) fn binary_name(&self) -> Option<&'static str> {
+        match self {
+            Executable::Rustc | Executable::RustFix => Some("rustc"),
+            Executable::Clippy | Executable::ClippyFix => Some("clippy-driver"),
+            Executable::Rustdoc => Some("rustdoc"),
+            Executable::Rustfmt => Some("rustfmt"),
+            // run via "cargo +toolchain miri"
+            Executable::Miri => Some("cargo-miri"),
+            Executable::RustAnalyzer
+            | Executable::Cranelift
+            | Executable::Kani
+            | Executable::RustcCodegenGCC
+            | Executable::Marker => None,
+        }
+    }
+
+    /// the binary of the tool inside another rustup toolchain
+    pub(crate) fn path_in_toolchain(&self, toolchain: &str) -> Option<PathBuf> {
+        let binary = self.binary_name()?;
+        let mut p = home::rustup_home().ok()?;
+        p.push("toolchains");
+        p.push(toolchain);
+        p.push("bin");
+        p.push(binary);
+        Some(p)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::ice::Executable;
+    use crate::ice::{Executable, UbCategory, UbKind};
 
     #[test]
     fn exec_rustc() {
@@ -624,4 +1231,82 @@ mod tests {
         // not master toolchain, but nightly
         assert!(ex.contains("miri"));
     }
+
+    #[test]
+    fn exec_in_toolchain() {
+        let ex = Executable::Clippy
+            .path_in_toolchain("stable-x86_64-unknown-linux-gnu")
+            .unwrap();
+        assert!(ex.ends_with("toolchains/stable-x86_64-unknown-linux-gnu/bin/clippy-driver"));
+        assert!(Executable::Kani.path_in_toolchain("master").is_none());
+    }
+
+    #[test]
+    fn ub_classification() {
+        let output = "error: Undefined Behavior: attempting a read access using <2462> at alloc1037[0x0], but that tag does not exist in the borrow stack for this location
+  --> src/main.rs:6:14
+   |
+6  |     unsafe { *x = 4 };
+   |              ^^^^^^
+   |
+   = help: this indicates a potential bug in the program: it performed an invalid operation, but the Stacked Borrows rules it violated are still experimental
+   = help: see https://github.com/rust-lang/unsafe-code-guidelines/blob/master/wip/stacked-borrows.md for further information
+";
+        let ub_line = output.lines().next().unwrap();
+        let ub = UbKind::from_miri_output(ub_line, output);
+        assert_eq!(ub.category, UbCategory::Aliasing);
+        assert_eq!(ub.span.as_deref(), Some("src/main.rs:6:14"));
+        assert_eq!(ub.help.len(), 2);
+
+        let ub_line = "error: Undefined Behavior: using uninitialized data, but this operation requires initialized memory";
+        let ub = UbKind::from_miri_output(ub_line, ub_line);
+        assert_eq!(ub.category, UbCategory::Uninit);
+
+        let category = |ub_line: &str| UbKind::from_miri_output(ub_line, ub_line).category;
+        assert_eq!(
+            category("error: Undefined Behavior: accessing memory with alignment 1, but alignment 4 is required"),
+            UbCategory::Misalignment
+        );
+        assert_eq!(
+            category("error: Undefined Behavior: misaligned pointer dereference: address must be a multiple of 0x4 but is 0x21"),
+            UbCategory::Misalignment
+        );
+        assert_eq!(
+            category("error: Undefined Behavior: deallocating alloc1, which has size 4 and alignment 4, using size 8 and alignment 4"),
+            UbCategory::Other
+        );
+    }
+
+    #[test]
+    fn old_errors_json() {
+        // written before UbKind had categories
+        let old = r#"[
+  {
+    "regresses_on": "Master",
+    "needs_feature": false,
+    "file": "tests/ui/foo.rs",
+    "args": ["-Zmiri-disable-isolation"],
+    "error_reason": "Undefined Behavior",
+    "ice_msg": "error: Undefined Behavior: using uninitialized data",
+    "executable": "Miri",
+    "query_stack": [],
+    "kind": { "Ub": "Uninteresting" }
+  }
+]"#;
+        let tempdir = tempdir::TempDir::new("icemaker_old_errors_json").unwrap();
+        let errors_json = tempdir.path().join("errors.json");
+        std::fs::write(&errors_json, old).unwrap();
+        let ices = crate::ice::read_errors_json(&errors_json);
+        assert_eq!(ices.len(), 1);
+        let crate::ice::ICEKind::Ub(ub) = &ices[0].kind else {
+            panic!("not ub: {:?}", ices[0].kind);
+        };
+        assert!(!ub.interesting);
+        assert_eq!(ub.category, UbCategory::Other);
+
+        // and the current format still round trips
+        let json = serde_json::to_string(&ices).unwrap();
+        let again: Vec<crate::ice::ICE> = serde_json::from_str(&json).unwrap();
+        assert_eq!(again[0].kind, ices[0].kind);
+    }
 }
@@ -91,12 +91,79 @@ pub(crate) struct Args {
     // read ice json and reduce, bisect, gen report?
     #[clap(long)]
     pub(crate) reduce: bool,
+    // how many findings --reduce works on at the same time, limited by the available memory
+    #[clap(long)]
+    pub(crate) reduce_jobs: Option<usize>,
+    // give up on reducing a finding after this many seconds
+    #[clap(long, default_value_t = 120)]
+    pub(crate) reduce_timeout: u64,
+    // find the nightly that introduced each finding of the errors.json
+    #[clap(long)]
+    pub(crate) bisect: bool,
+    // dir with dated nightly-YYYY-MM-DD toolchains for --bisect, defaults to the rustup toolchains
+    #[clap(long)]
+    pub(crate) toolchains_dir: Option<std::path::PathBuf>,
+    // check the corpus on rustup toolchain A and every file that does not crash there as recorded on
+    // B as well, record what regressed or got fixed from A to B
+    #[clap(long, num_args = 2, value_names = ["A", "B"])]
+    pub(crate) diff_toolchains: Vec<String>,
+    // find code that compiles on stable but not on master or the other way around
+    #[clap(long)]
+    pub(crate) accept_reject: bool,
+    // only check the part i of n of the files, see shard.rs
+    #[clap(long, value_name = "i/n")]
+    pub(crate) shard: Option<crate::shard::Shard>,
+    // merge the errors.json files of several shards into the errors.json of this dir, one finding per
+    // fingerprint
+    #[clap(long, num_args = 1..)]
+    pub(crate) merge: Vec<std::path::PathBuf>,
+    // stop starting new work after this many minutes, the most promising files go first
+    #[clap(long, value_name = "MINUTES")]
+    pub(crate) time_budget: Option<u64>,
+    // do not start tool processes beyond this much RAM, defaults to the memory available at start
+    #[clap(long, value_name = "GB")]
+    pub(crate) memory_budget: Option<u64>,
+    // do not start tool processes while there is less free disk space than this
+    #[clap(long, value_name = "GB", default_value_t = 5)]
+    pub(crate) min_free_disk: u64,
     // skip report generation to avoid running into OOMS or disk issues etc
     #[clap(long)]
     pub(crate) skip_report: bool,
 
     #[clap(long)]
     pub(crate) marker: bool,
+
+    // local json export of the rust-lang/rust issues, used to find possible duplicates of our findings
+    #[clap(long)]
+    pub(crate) issue_dump: Option<std::path::PathBuf>,
+
+    // print the findings from errors.json instead of checking anything
+    #[clap(long)]
+    pub(crate) list: bool,
+
+    // render the findings of all projects into a static html site
+    #[clap(long)]
+    pub(crate) html: bool,
+
+    // do not report miri findings of these categories, pass an empty list to report everything
+    #[clap(long, value_enum, num_args = 0.., default_values_t = [crate::ice::UbCategory::Leak, crate::ice::UbCategory::Unsupported])]
+    pub(crate) miri_suppress: Vec<crate::ice::UbCategory>,
+
+    // write files that routinely hit the timeout as candidates for EXCEPTIONS and MIRI_EXCEPTIONS
+    #[clap(long)]
+    pub(crate) exception_candidates: bool,
+
+    // exit with 0 if --candidate still reproduces the finding in this json file, used as oracle for reducers
+    #[clap(long)]
+    pub(crate) interesting: Option<std::path::PathBuf>,
+
+    // candidate file for --interesting
+    #[clap(long)]
+    pub(crate) candidate: Option<std::path::PathBuf>,
+
+    // --interesting: the candidate also has to compile without any flags
+    #[clap(long)]
+    pub(crate) candidate_must_compile: bool,
 }
 
 /// check whether a file uses features or not
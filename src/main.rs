@@ -1,6 +1,5 @@
 #![feature(variant_count)]
 
-mod flags;
 /// Run rustc its own tests with different parameters
 /// If an ICE (internal compiler error/crash/panic) is found, find out
 /// the smallest combination of responsible flags and save data about the crash
@@ -28,30 +27,62 @@ mod flags;
 //
 //  for file in `cat errors.json | grep file.: | cut -d' ' -f6 | sed s/\"//g | sed s/,//` ; do; echo -n "$file " ; grep "unsafe\|simd\|no_core\|transmute\|Box::\|rustc_variance" -c $file ; done  | grep 0$
 //
+mod accept_reject;
+mod bisect;
+mod debug_assertions;
+mod duplicates;
+mod editions;
+mod flag_minimizer;
+mod flags;
 mod fuzz;
 mod fuzz2;
 mod fuzz_tree_splicer;
+mod html;
 mod ice;
 mod library;
+mod memory_limiter;
+mod normalize;
 mod printing;
+mod prioritize;
+mod reduce;
+mod reduction_queue;
 mod run_commands;
+mod shard;
 mod smolfuzz;
-
+mod summary;
+mod tempdirs;
+mod timings;
+mod toolchain_diff;
+mod triage;
+
+use crate::accept_reject::accept_reject_findings;
+use crate::bisect::bisect_findings;
+use crate::editions::EDITION_FLAGS;
+use crate::flag_minimizer::minimize_flags;
 use crate::flags::*;
 use crate::fuzz::*;
 use crate::fuzz_tree_splicer::*;
 use crate::ice::*;
 use crate::library::*;
+use crate::memory_limiter::{waited_for_memory, MEMORY_LIMITER};
 use crate::printing::*;
+use crate::prioritize::{budget_spent, prioritize_files, prioritize_flag_chunks, DEADLINE};
+use crate::reduce::*;
+use crate::reduction_queue::reduce_findings;
 use crate::run_commands::*;
+use crate::shard::merge_errors_jsons;
 use crate::smolfuzz::*;
+use crate::summary::*;
+use crate::tempdirs::init_run_tempdir;
+use crate::timings::*;
+use crate::toolchain_diff::{diff_findings, files_for_candidate};
 
 use std::collections::HashSet;
 use std::ffi::{OsStr, OsString};
 use std::io::BufRead;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output, Stdio};
+use std::process::{Command, Output};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -119,29 +150,12 @@ fn check_dir(
     global_tempdir_path: &PathBuf,
     timer: &Timer,
     errors_json_tmp: &Arc<Mutex<std::fs::File>>,
-) -> Vec<PathBuf> {
+) -> ProjectSummary {
     // read in existing errors
     // read the string INTO Vec<ICE>
 
     let errors_json = root_path.join("errors.json");
-    let errors_before: Vec<ICE> = if errors_json.exists() {
-        let read = match std::fs::read_to_string(&errors_json) {
-            Ok(content) => content,
-            Err(_) => panic!("failed to read '{}'", errors_json.display()),
-        };
-        match serde_json::from_str(&read) {
-            Ok(previous_errors) => previous_errors,
-            Err(e) => {
-                // this can happen if we for example change the representation of Ice so that that the previous file is no longer compatible with the new format
-                eprintln!("Failed to parse errors.json, is it a json file?");
-                eprintln!("original error: '{e:?}'");
-                Vec::new()
-            }
-        }
-    } else {
-        // we don't have a file, start blank
-        Vec::new()
-    };
+    let mut errors_before: Vec<ICE> = read_errors_json(&errors_json);
 
     let executable = Executable::from(args);
     let executables = if !matches!(executable, Executable::Rustc) ||  /* may have passed --rustc to disable clippy rustdoc etc */ args.rustc
@@ -186,28 +200,27 @@ fn check_dir(
 
     if args.smolfuzz {
         codegen_smolfuzz();
-        return Vec::new();
+        return ProjectSummary::default();
+    }
+
+    // only reads the errors.json, nothing to set up
+    if args.list {
+        list_findings(&errors_before);
+        return ProjectSummary::default();
     }
 
     if executables.contains(&&Executable::Miri) || matches!(executable, Executable::Miri) {
-        println!("Running cargo miri setup");
-        let _ = std::process::Command::new("cargo")
-            .arg(if args.local_debug_assertions {
-                "+local-debug-assertions"
-            } else {
-                "+master"
-            })
-            .arg("miri")
-            .arg("setup")
-            .status()
-            .unwrap()
-            .success();
+        let _ = miri_setup(if args.local_debug_assertions {
+            "local-debug-assertions"
+        } else {
+            "master"
+        });
     }
 
     if args.heat {
         let chain_order = args.chain_order;
         let _ = run_space_heater(executable, chain_order, global_tempdir_path);
-        return Vec::new();
+        return ProjectSummary::default();
     }
 
     if args.codegen_splice {
@@ -222,10 +235,10 @@ fn check_dir(
 
     if args.fuzz {
         let _ = run_random_fuzz(executable, global_tempdir_path);
-        return Vec::new();
+        return ProjectSummary::default();
     } else if args.fuzz2 {
         crate::fuzz2::fuzz2::fuzz2main();
-        return Vec::new();
+        return ProjectSummary::default();
     }
 
     if args.incr_fuzz {
@@ -234,7 +247,38 @@ fn check_dir(
     }
 
     if (args).reduce {
-        reduce_all(global_tempdir_path);
+        let jobs = args.reduce_jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+        reduce_all(global_tempdir_path, jobs);
+        std::process::exit(0);
+    }
+
+    if args.bisect {
+        let toolchains_dir = args.toolchains_dir.clone().unwrap_or_else(|| {
+            home::rustup_home()
+                .expect("could not find the rustup home")
+                .join("toolchains")
+        });
+        let errors = bisect_findings(errors_before, &toolchains_dir, global_tempdir_path);
+        let errors_new = serde_json::to_string_pretty(&errors).expect("failed to jsonify errors");
+        std::fs::write(&errors_json, errors_new)
+            .unwrap_or_else(|_| panic!("error: failed to write to {}", errors_json.display()));
+        std::process::exit(0);
+    }
+
+    if !args.merge.is_empty() {
+        // keep what we already have in front so that merging twice changes nothing
+        let errors_jsons = std::iter::once(errors_json.clone())
+            .chain(args.merge.iter().cloned())
+            .collect::<Vec<PathBuf>>();
+        let errors = merge_errors_jsons(&errors_jsons);
+        eprintln!("{} findings after merging", errors.len());
+        let errors_new = serde_json::to_string_pretty(&errors).expect("failed to jsonify errors");
+        std::fs::write(&errors_json, errors_new)
+            .unwrap_or_else(|_| panic!("error: failed to write to {}", errors_json.display()));
         std::process::exit(0);
     }
 
@@ -247,10 +291,50 @@ fn check_dir(
         .map(|f| f.path().to_owned())
         .collect::<Vec<PathBuf>>();
 
+    if let Some(shard) = args.shard {
+        files.retain(|file| shard.contains(file));
+    }
+
     // check biggest files first
     files.par_sort_by_cached_key(|file| std::fs::metadata(file).unwrap().len());
     files.reverse();
 
+    // with a time budget, most promising files and flag chunks first
+    let rustc_flags = if args.time_budget.is_some() {
+        let history = TimingHistory::load(Path::new(TIMINGS_HISTORY_FILE));
+        prioritize_files(&mut files, root_path, &errors_before, &history);
+        prioritize_flag_chunks(&RUSTC_FLAGS, &history)
+    } else {
+        RUSTC_FLAGS.to_vec()
+    };
+
+    if args.accept_reject {
+        let mut findings = accept_reject_findings(&files, global_tempdir_path)
+            .into_iter()
+            .map(ICE::with_triage)
+            .collect::<Vec<ICE>>();
+        findings
+            .iter()
+            .for_each(|ice| println!("{}", ice.to_printable()));
+        strip_root_path(&mut findings, root_path);
+
+        // the new result for a file replaces the old one, a file that does not differ anymore
+        // drops its old one
+        let checked_files = files
+            .iter()
+            .map(|file| relative_to_root(file, root_path))
+            .collect::<Vec<PathBuf>>();
+        let mut errors = errors_before;
+        errors.retain(|ice| {
+            !(matches!(ice.kind, ICEKind::AcceptReject(_)) && checked_files.contains(&ice.file))
+        });
+        errors.extend(findings);
+        let errors_new = serde_json::to_string_pretty(&errors).expect("failed to jsonify errors");
+        std::fs::write(&errors_json, errors_new)
+            .unwrap_or_else(|_| panic!("error: failed to write to {}", errors_json.display()));
+        std::process::exit(0);
+    }
+
     /*
     eprintln!("\n\nSTART\n\n");
 
@@ -331,94 +415,81 @@ fn check_dir(
             .collect::<Vec<_>>();
 
         dbg!(incr_crashes);
-        return Vec::new();
+        return ProjectSummary::default();
     }
 
-    // main loop that checks all the files
-    let mut errors: Vec<ICE> = files
-        .par_iter()
-        .flat_map(|file| {
-            // for each file, increment counter by one
-            let _ = counter.fetch_add(1, Ordering::SeqCst);
-            executables
-                .par_iter()
-                .panic_fuse()
-                // don't check anything that is contained in the exception list
-                .filter(|executable| {
-                    !EXCEPTION_LIST.contains(file)
-                        || (matches!(executable, Executable::Miri)
-                            || (matches!(executable, Executable::Cranelift))
-                                && !MIRI_EXCEPTION_LIST.contains(file))
-                })
-                .map(|executable| {
-                    let executable_start_time = Instant::now();
-
-                    let exec_path = executable.path();
-
-                    match executable {
-                        Executable::Rustc
-                        /* | Executable::CraneliftLocal */ => {
-                            // with expensive flags, run on each of the editions separately
-                            let editions = if args.expensive_flags {
-                                vec!["--edition=2015", "--edition=2018", "--edition=2021"]
-                            } else {
-                                // FIXME need to have somehting here to at least iter once :/
-                                vec!["-Ccodegen-units=1"]
-                            };
-                            // for each file, run every chunk of RUSTC_FLAGS and check it and see if it crashes
-                            RUSTC_FLAGS
-                                // note: this can be dangerous in case of max memory usage, if a file needs a lot
-                                .par_iter()
-                                .panic_fuse()
-                                .map(|flag_combinations| flag_combinations.iter())
-                                // need shit to flat map a sequential iter into a par_iter
-                                .flat_map_iter(|flag_combinations| {
-                                    editions.iter().map(move |x| {
-                                        if args.expensive_flags {
-                                            flag_combinations
-                                                .clone()
-                                                .chain(std::iter::once(x).take(1))
-                                        } else {
-                                            flag_combinations
-                                                .clone()
-                                                .chain(std::iter::once(&"").take(0))
-                                        }
-                                    })
-                                })
-                                .map(|flag_combination| {
-                                    let ice = ICE::discover(
-                                        file,
-                                        &exec_path,
-                                        executable,
-                                        flag_combination,
-                                        &[],
-                                        false,
-                                        &counter,
-                                        files.len(),
-                                        args.silent,
-                                        global_tempdir_path,
-                                    );
-                                    let seconds_elapsed =
-                                        executable_start_time.elapsed().as_millis() as usize;
-                                    timer.update_from_executable(executable, seconds_elapsed);
-
-                                    ice
-                                })
-                                .collect::<Vec<Option<ICE>>>()
-                        }
-                        Executable::Miri => MIRIFLAGS
-                            .par_iter()
-                            .map(|miri_flag_combination| {
-                                MIRI_RUSTFLAGS
+    // main loop that checks all the files, with the executables of the rustup toolchain if one is given
+    let run_corpus = |files: &[PathBuf], toolchain: Option<&str>| -> Vec<ICE> {
+        counter.store(0, Ordering::SeqCst);
+        // par_bridge hands out the files in order, par_iter would split the list up front
+        let files_iter = if DEADLINE.is_some() {
+            rayon::iter::Either::Left(files.iter().par_bridge())
+        } else {
+            rayon::iter::Either::Right(files.par_iter())
+        };
+        files_iter
+            .flat_map(|file| {
+                if budget_spent() {
+                    return Vec::new();
+                }
+                // for each file, increment counter by one
+                let _ = counter.fetch_add(1, Ordering::SeqCst);
+                executables
+                    .par_iter()
+                    .panic_fuse()
+                    // don't check anything that is contained in the exception list
+                    .filter(|executable| {
+                        !EXCEPTION_LIST.contains(file)
+                            || (matches!(executable, Executable::Miri)
+                                || (matches!(executable, Executable::Cranelift))
+                                    && !MIRI_EXCEPTION_LIST.contains(file))
+                    })
+                    .map(|executable| {
+                        let executable_start_time = Instant::now();
+
+                        let exec_path = match toolchain {
+                            Some(toolchain) => match executable.path_in_toolchain(toolchain) {
+                                Some(path) => path.display().to_string(),
+                                None => return Vec::new(),
+                            },
+                            None => executable.path(),
+                        };
+
+                        match executable {
+                            Executable::Rustc
+                            /* | Executable::CraneliftLocal */ => {
+                                // with expensive flags, run on each of the editions separately
+                                let editions = if args.expensive_flags {
+                                    EDITION_FLAGS.to_vec()
+                                } else {
+                                    // FIXME need to have somehting here to at least iter once :/
+                                    vec!["-Ccodegen-units=1"]
+                                };
+                                // for each file, run every chunk of RUSTC_FLAGS and check it and see if it crashes
+                                rustc_flags
+                                    // note: this can be dangerous in case of max memory usage, if a file needs a lot
                                     .par_iter()
                                     .panic_fuse()
-                                    .map(|miri_rustflag| {
+                                    .filter(|_| !budget_spent())
+                                    // need shit to flat map a sequential iter into a par_iter
+                                    .flat_map_iter(|flag_combinations| {
+                                        editions.iter().map(move |x| {
+                                            (flag_combinations, args.expensive_flags.then_some(*x))
+                                        })
+                                    })
+                                    .map(|(flag_combinations, edition)| {
+                                        let flags = flag_combinations
+                                            .iter()
+                                            .copied()
+                                            .chain(edition)
+                                            .collect::<Vec<&str>>();
+                                        let sample_start_time = Instant::now();
                                         let ice = ICE::discover(
                                             file,
                                             &exec_path,
                                             executable,
-                                            *miri_rustflag,
-                                            miri_flag_combination,
+                                            flags.iter(),
+                                            &[],
                                             false,
                                             &counter,
                                             files.len(),
@@ -428,51 +499,147 @@ fn check_dir(
                                         let seconds_elapsed =
                                             executable_start_time.elapsed().as_millis() as usize;
                                         timer.update_from_executable(executable, seconds_elapsed);
+                                        // prioritize_flag_chunks looks the chunk up without the edition
+                                        timer.record_sample(file, executable, flag_combinations, sample_start_time, &ice);
 
                                         ice
                                     })
-                                    .find_any(|ice| ice.is_some())
-                            })
-                            .flatten()
-                            .collect::<Vec<Option<ICE>>>(),
-                        _ => {
-                            // if we run clippy/rustfmt/rla .. we dont need to check multiple combinations of RUSTFLAGS
-                            let ice = vec![ICE::discover(
-                                file,
-                                &exec_path,
-                                executable,
-                                // run with no flags
-                                &[],
-                                &[],
-                                false,
-                                &counter,
-                                files.len(),
-                                args.silent,
-                                global_tempdir_path,
-                            )];
-                            let seconds_elapsed =
-                                executable_start_time.elapsed().as_millis() as usize;
-                            timer.update_from_executable(executable, seconds_elapsed);
-                            ice
+                                    .collect::<Vec<Option<ICE>>>()
+                            }
+                            Executable::Miri => MIRIFLAGS
+                                .par_iter()
+                                .map(|miri_flag_combination| {
+                                    MIRI_RUSTFLAGS
+                                        .par_iter()
+                                        .panic_fuse()
+                                        .map(|miri_rustflag| {
+                                            let sample_start_time = Instant::now();
+                                            let ice = ICE::discover(
+                                                file,
+                                                &exec_path,
+                                                executable,
+                                                *miri_rustflag,
+                                                miri_flag_combination,
+                                                false,
+                                                &counter,
+                                                files.len(),
+                                                args.silent,
+                                                global_tempdir_path,
+                                            );
+                                            let seconds_elapsed =
+                                                executable_start_time.elapsed().as_millis() as usize;
+                                            timer.update_from_executable(executable, seconds_elapsed);
+                                            let flags = [*miri_rustflag, miri_flag_combination].concat();
+                                            timer.record_sample(file, executable, &flags, sample_start_time, &ice);
+
+                                            ice
+                                        })
+                                        .find_any(|ice| ice.is_some())
+                                })
+                                .flatten()
+                                .collect::<Vec<Option<ICE>>>(),
+                            _ => {
+                                // if we run clippy/rustfmt/rla .. we dont need to check multiple combinations of RUSTFLAGS
+                                let sample_start_time = Instant::now();
+                                let ice = ICE::discover(
+                                    file,
+                                    &exec_path,
+                                    executable,
+                                    // run with no flags
+                                    &[],
+                                    &[],
+                                    false,
+                                    &counter,
+                                    files.len(),
+                                    args.silent,
+                                    global_tempdir_path,
+                                );
+                                let seconds_elapsed =
+                                    executable_start_time.elapsed().as_millis() as usize;
+                                timer.update_from_executable(executable, seconds_elapsed);
+                                timer.record_sample(file, executable, &[], sample_start_time, &ice);
+                                vec![ice]
+                            }
                         }
-                    }
-                })
-                .flatten()
-                .filter(|opt_ice| opt_ice.is_some())
-                .map(|ice| ice.unwrap())
-                .map(|ice| {
-                    // note: we may panic here (inside the thread) if we run out of disk space and thus failt to write further ICEs to disk
-                    let ice_json =
-                        serde_json::to_string_pretty(&ice).expect("failed ot jsonify ICE");
-                    let errors_tmp = Arc::clone(errors_json_tmp);
-                    let mut f = errors_tmp.lock().unwrap();
-                    writeln!(f, "{}", ice_json)
-                        .expect("failed to write to mutex locked errors_tmp.json");
-                    ice
-                })
-                .collect::<Vec<ICE>>()
-        })
-        .collect();
+                    })
+                    .flatten()
+                    .filter(|opt_ice| opt_ice.is_some())
+                    .map(|ice| ice.unwrap())
+                    .map(|ice| {
+                        // note: we may panic here (inside the thread) if we run out of disk space and thus failt to write further ICEs to disk
+                        let ice_json =
+                            serde_json::to_string_pretty(&ice).expect("failed ot jsonify ICE");
+                        let errors_tmp = Arc::clone(errors_json_tmp);
+                        let mut f = errors_tmp.lock().unwrap();
+                        writeln!(f, "{}", ice_json)
+                            .expect("failed to write to mutex locked errors_tmp.json");
+                        ice
+                    })
+                    .collect::<Vec<ICE>>()
+            })
+            .collect()
+    };
+
+    if let [baseline, candidate] = args.diff_toolchains.as_slice() {
+        let baseline_findings = run_corpus(&files, Some(baseline));
+        let mut relative_findings = baseline_findings.clone();
+        strip_root_path(&mut relative_findings, root_path);
+        let recorded = errors_before
+            .iter()
+            .filter(|ice| executables.contains(&&ice.executable))
+            .cloned()
+            .collect::<Vec<ICE>>();
+        let candidate_files = files_for_candidate(
+            &files,
+            |file| relative_to_root(file, root_path),
+            &relative_findings,
+            &recorded,
+        );
+        eprintln!(
+            "checking {} of {} files on {candidate}, the others crash on {baseline} as before",
+            candidate_files.len(),
+            files.len()
+        );
+        // a known crash of a skipped file is not a fix
+        let baseline_findings = baseline_findings
+            .into_iter()
+            .filter(|ice| candidate_files.contains(&ice.file))
+            .collect::<Vec<ICE>>();
+        let candidate_findings = run_corpus(&candidate_files, Some(candidate));
+        let mut diff = diff_findings(
+            (baseline, &baseline_findings),
+            (candidate, &candidate_findings),
+            global_tempdir_path,
+        )
+        .into_iter()
+        .map(ICE::with_triage)
+        .collect::<Vec<ICE>>();
+        diff.iter().for_each(|ice| {
+            if let Some(toolchain_diff) = &ice.toolchain_diff {
+                println!("{}\n    {toolchain_diff}", ice.to_printable());
+            }
+        });
+
+        strip_root_path(&mut diff, root_path);
+
+        // a new diff replaces the result of an earlier one for the same finding
+        let mut errors = errors_before;
+        errors.retain(|ice| !diff.contains(ice));
+        errors.extend(diff);
+        let errors_new = serde_json::to_string_pretty(&errors).expect("failed to jsonify errors");
+        std::fs::write(&errors_json, errors_new)
+            .unwrap_or_else(|_| panic!("error: failed to write to {}", errors_json.display()));
+        std::process::exit(0);
+    }
+
+    let mut errors = run_corpus(&files, None);
+    if budget_spent() {
+        eprintln!(
+            "\ntime budget spent after checking {} of {} files",
+            counter.load(Ordering::SeqCst),
+            files.len()
+        );
+    }
 
     // dedupe equal ICEs, before sorting
     errors.dedup();
@@ -486,8 +653,12 @@ fn check_dir(
     flagless_ices.iter().for_each(|flglice| {
         // if we have an ICE where the msg and the file is equal to a flagless ice (but the ice is not the flagless ice), assume that the flags are unrelated
         // remove the ice from "errors"
+        // miri findings always carry their flags, a flagless rustc ice says nothing about them
         errors.retain(|ice| {
-            !(ice.file == flglice.file && ice.ice_msg == flglice.ice_msg && !ice.args.is_empty())
+            !(ice.file == flglice.file
+                && ice.ice_msg == flglice.ice_msg
+                && ice.executable == flglice.executable
+                && !ice.args.is_empty())
         });
     });
 
@@ -516,6 +687,45 @@ fn check_dir(
             ice.ice_msg
         )
     });
+
+    adopt_miri_flags(&mut errors_before, &errors);
+
+    // find out on which editions and builds (release, debug assertions) the findings reproduce,
+    // known findings keep the result of the last run as well as the results of --reduce, --bisect
+    // and --diff-toolchains. Past the --time-budget the editions stay unknown
+    let with_editions = |ice: ICE| {
+        if budget_spent() {
+            ice
+        } else {
+            ice.with_editions(global_tempdir_path)
+        }
+    };
+    let mut errors = errors
+        .into_par_iter()
+        .map(
+            |mut ice| match errors_before.iter().find(|before| **before == ice) {
+                Some(before) => {
+                    ice.ice_on_valid = before.ice_on_valid;
+                    ice.regression_window.clone_from(&before.regression_window);
+                    ice.toolchain_diff.clone_from(&before.toolchain_diff);
+                    ice.debug_assertions = before.debug_assertions;
+                    ice.duplicate_files.clone_from(&before.duplicate_files);
+                    if before.editions.is_empty() {
+                        with_editions(ice)
+                    } else {
+                        ICE {
+                            editions: before.editions.clone(),
+                            ..ice
+                        }
+                    }
+                }
+                None => with_editions(ice),
+            },
+        )
+        .map(ICE::with_debug_assertions)
+        .map(ICE::with_triage)
+        .collect::<Vec<ICE>>();
+
     // original sorting again
     errors.sort_by_key(|ice| ice.file.clone());
     errors.sort_by_key(|ice| ice.ice_msg.clone());
@@ -534,18 +744,7 @@ fn check_dir(
     });
     */
 
-    // if the ices start with the root path, we need to strip the root path
-    errors.iter_mut().for_each(|ice| {
-        let mut ice_path = ice.file.clone();
-        if ice_path.starts_with(root_path) {
-            ice_path = ice_path
-                .strip_prefix(root_path)
-                .expect("strip_prefix failed, could not fix ice.file")
-                .to_owned();
-            // readd the leading "./" that was stripped previously
-            ice.file = PathBuf::from("./").join(ice_path);
-        }
-    });
+    strip_root_path(&mut errors, root_path);
 
     // errors.iter().for_each(|ice| ice.to_disk());
 
@@ -635,7 +834,62 @@ fn check_dir(
         });
         */
 
-    files
+    ProjectSummary::new(root_path, files.len(), &errors, &errors_before)
+}
+
+// if the ices start with the root path, we need to strip the root path
+fn strip_root_path(errors: &mut [ICE], root_path: &Path) {
+    errors.iter_mut().for_each(|ice| {
+        ice.file = relative_to_root(&ice.file, root_path);
+    });
+}
+
+/// the path of a file as it is stored in the errors.json
+fn relative_to_root(path: &Path, root_path: &Path) -> PathBuf {
+    match path.strip_prefix(root_path) {
+        // readd the leading "./" that was stripped previously
+        Ok(relative) => PathBuf::from("./").join(relative),
+        Err(_) => path.to_owned(),
+    }
+}
+
+/// miri findings used to be stored without their flags. An entry of an older errors.json takes the
+/// flags of the finding it matches, otherwise every known miri finding would show up as new once
+fn adopt_miri_flags(errors_before: &mut [ICE], errors: &[ICE]) {
+    errors_before
+        .iter_mut()
+        .filter(|before| before.executable == Executable::Miri && before.args.is_empty())
+        .for_each(|before| {
+            if let Some(ice) = errors.iter().find(|ice| {
+                ice.executable == Executable::Miri
+                    && ICE {
+                        args: Vec::new(),
+                        ..(*ice).clone()
+                    } == *before
+            }) {
+                before.args.clone_from(&ice.args);
+            }
+        });
+}
+
+/// print all findings of a project, annotated with possible duplicates from the --issue-dump
+fn list_findings(ices: &[ICE]) {
+    ices.iter().for_each(|ice| {
+        println!("{}", ice.to_printable());
+        let triage = ice.triage_line();
+        if !triage.is_empty() {
+            println!("    {triage}");
+        }
+        if let Some(toolchain_diff) = &ice.toolchain_diff {
+            println!("    {toolchain_diff}");
+        }
+        if let Some(dup) = crate::duplicates::ISSUE_DUMP
+            .as_ref()
+            .and_then(|dump| dump.best_match(ice))
+        {
+            println!("    {dup}");
+        }
+    });
 }
 
 #[derive(Debug, Default)]
@@ -652,6 +906,7 @@ struct Timer {
     kani_time: AtomicUsize,
     rustc_codegen_gcc_time: AtomicUsize,
     marker_time: AtomicUsize,
+    samples: TimingRecorder,
 }
 
 impl Timer {
@@ -715,6 +970,56 @@ impl Timer {
         }
     }
 
+    fn record_sample(
+        &self,
+        file: &Path,
+        exe: &Executable,
+        flags: &[&str],
+        start_time: Instant,
+        ice: &Option<ICE>,
+    ) {
+        let timed_out = matches!(
+            ice,
+            Some(ICE {
+                kind: ICEKind::Hang(_),
+                ..
+            })
+        );
+        self.samples.record(
+            file,
+            exe,
+            flags,
+            start_time.elapsed().as_millis(),
+            timed_out,
+            ice.is_some() && !timed_out,
+        );
+    }
+
+    fn seconds_per_executable(&self) -> std::collections::BTreeMap<String, u64> {
+        [
+            (Executable::Rustc, &self.rustc_time),
+            (Executable::Clippy, &self.clippy_time),
+            (Executable::Rustdoc, &self.rustdoc_time),
+            (Executable::RustAnalyzer, &self.rla_time),
+            (Executable::Rustfmt, &self.rustfmt_time),
+            (Executable::Miri, &self.miri_time),
+            (Executable::Cranelift, &self.craneliftlocal_time),
+            (Executable::ClippyFix, &self.clippyfix_time),
+            (Executable::RustFix, &self.rustfix_time),
+            (Executable::Kani, &self.kani_time),
+            (Executable::RustcCodegenGCC, &self.rustc_codegen_gcc_time),
+            (Executable::Marker, &self.marker_time),
+        ]
+        .into_iter()
+        .map(|(exe, millis)| {
+            (
+                format!("{exe:?}"),
+                millis.load(Ordering::SeqCst) as u64 / 1000,
+            )
+        })
+        .collect()
+    }
+
     fn new() -> Self {
         Timer::default()
     }
@@ -761,6 +1066,7 @@ impl Timer {
             marker_time: AtomicUsize::new(
                 Duration::from_millis(self.marker_time.into_inner() as u64).as_secs() as usize,
             ),
+            samples: self.samples,
         }
     }
 }
@@ -768,6 +1074,7 @@ impl Timer {
 fn main() {
     // how long did we take?
     let global_start_time = Instant::now();
+    Lazy::force(&DEADLINE);
 
     // do not dump backtraces to disk all the time
     // RUSTC_ICE=..
@@ -776,6 +1083,14 @@ fn main() {
 
     let args = Args::parse();
 
+    if let Some(ice_json) = &args.interesting {
+        let candidate = args
+            .candidate
+            .as_ref()
+            .expect("--interesting needs a --candidate file");
+        interesting_main(ice_json, candidate, args.candidate_must_compile);
+    }
+
     // if we have a --global-tempdir-path passed, create all tempdirs in there, otherwise use TempDir default which is /tmp/
     let global_tempdir = if let Some(ref custom_tempdir_path) = args.global_tempdir_path {
         let mut custom_tmpdir = std::path::PathBuf::from(&custom_tempdir_path);
@@ -797,6 +1112,9 @@ fn main() {
         TempDir::new("icemaker_global_tempdir").expect("failed to create global icemaker tempdir")
     };
 
+    // clean up after runs that crashed
+    init_run_tempdir(global_tempdir.path());
+
     let global_tempdir_path_closure: PathBuf = global_tempdir.path().to_owned();
     let global_tempdir_path: PathBuf = global_tempdir_path_closure.clone();
 
@@ -814,6 +1132,8 @@ fn main() {
         }
     );
 
+    eprintln!("memory budget: {} MB", MEMORY_LIMITER.budget_kb() / 1000);
+
     // rayon thread pool so we can configure number of threads easily
     rayon::ThreadPoolBuilder::new()
         .num_threads(args.threads)
@@ -880,6 +1200,11 @@ fn main() {
         std::process::exit(1);
     }
 
+    if args.html {
+        crate::html::render_html(&projects);
+        return;
+    }
+
     let timer: Timer = Timer::new();
 
     let root_path = std::env::current_dir().expect("could not get CWD!");
@@ -889,15 +1214,31 @@ fn main() {
 
     // all checked files
 
-    let files = projects
+    let project_summaries = projects
         .iter()
         .map(|dir| check_dir(dir, &args, &global_tempdir_path, &timer, &errors_json_tmp))
-        .flat_map(|v| v.into_iter())
-        .collect::<Vec<PathBuf>>();
+        .collect::<Vec<ProjectSummary>>();
 
     let seconds_elapsed = global_start_time.elapsed().as_secs();
 
-    let number_of_checked_files = files.len();
+    let number_of_checked_files = project_summaries
+        .iter()
+        .map(|project| project.files_checked)
+        .sum::<usize>();
+
+    // don't overwrite the summary of the last real run with an empty one after --list etc.
+    let work_units = timer.samples.len();
+    if work_units > 0 {
+        RunSummary::new(
+            project_summaries,
+            work_units,
+            global_start_time.elapsed().as_secs_f64(),
+            timer.seconds_per_executable(),
+            &timer.samples.executables(),
+        )
+        .to_disk(&root_path.join(SUMMARY_FILE));
+    }
+
     if seconds_elapsed == 0 {
         println!("Checked {number_of_checked_files} files in <1 second");
         return;
@@ -910,6 +1251,11 @@ fn main() {
         files_per_second
     );
 
+    timer.samples.report(
+        &projects,
+        &root_path.join(TIMINGS_HISTORY_FILE),
+        args.exception_candidates,
+    );
     eprintln!("Timings in seconds:\n{:?}", timer.into_seconds());
 
     eprintln!("\n\nALL CRASHES\n\n");
@@ -951,6 +1297,12 @@ impl ICE {
         let compiler_flags = &compiler_flags.into_iter().cloned().collect::<Vec<&str>>()[..];
 
         let thread_start = Instant::now();
+        let waited_at_start = waited_for_memory();
+        // waiting for memory is not hanging
+        let seconds_running = || {
+            let waited = waited_for_memory() - waited_at_start;
+            thread_start.elapsed().saturating_sub(waited).as_secs()
+        };
         const SECONDS_LIMIT: u64 = PROCESS_TIMEOUT_S as u64;
         const _SECONDS_LIMIT_MIRI: u64 = 20;
 
@@ -976,53 +1328,15 @@ impl ICE {
 
         // note: `actual_args` are the VERY ORIGINAL ARGS so this contains something like -otmpdir_foo.AFXIU/outfile which will no longer be
         // a valid path as soon as the tempdir goes out of scope
-        let (cmd_output, _cmd, actual_args) = match executable {
-            Executable::Clippy => run_clippy(exec_path, file, global_tempdir_path),
-            Executable::ClippyFix => run_clippy_fix(exec_path, file, global_tempdir_path),
-            Executable::RustFix => run_rustfix(exec_path, file, global_tempdir_path),
-            Executable::Rustc => run_rustc(
-                exec_path,
-                file,
-                incremental,
-                compiler_flags,
-                global_tempdir_path,
-            ),
-            Executable::Rustdoc => run_rustdoc(exec_path, file, global_tempdir_path),
-            Executable::RustAnalyzer => {
-                run_compare_ra_to_rustc(exec_path, file, global_tempdir_path)
-            }
-            Executable::Rustfmt => run_rustfmt(exec_path, file, global_tempdir_path),
-            Executable::Miri => run_miri(
-                exec_path,
-                file,
-                miri_flags,
-                compiler_flags,
-                global_tempdir_path,
-            ),
-
-            Executable::Cranelift => {
-                let mut compiler_flags = compiler_flags.to_vec();
-                compiler_flags.push("-Zcodegen-backend=cranelift");
-                run_rustc(
-                    exec_path,
-                    file,
-                    incremental,
-                    &compiler_flags,
-                    global_tempdir_path,
-                )
-            }
-            Executable::Kani => run_kani(
-                exec_path,
-                file,
-                miri_flags, // hack
-                compiler_flags,
-                global_tempdir_path,
-            ),
-            Executable::RustcCodegenGCC => {
-                rustc_codegen_gcc_local(exec_path, file, false, compiler_flags, global_tempdir_path)
-            }
-            Executable::Marker => run_marker(file, compiler_flags, global_tempdir_path),
-        }
+        let (cmd_output, _cmd, actual_args) = run_executable(
+            executable,
+            exec_path,
+            file,
+            incremental,
+            compiler_flags,
+            miri_flags,
+            global_tempdir_path,
+        )
         .unwrap();
 
         // dbg!(&actual_args);
@@ -1041,7 +1355,7 @@ impl ICE {
                     os.push::<&OsString>(&OsString::from(tempdir_path.to_string()));
                     os.push::<&OsString>(&OsString::from("/outputfile"));
                     os
-                } else if arg.to_str().unwrap().starts_with("-Zdump-mir-dir)") {
+                } else if arg.to_str().unwrap().starts_with("-Zdump-mir-dir") {
                     let mut os = OsString::from("-Zdump-mir-dir=");
                     os.push::<&OsString>(&OsString::from(tempdir_path.to_string()));
 
@@ -1144,6 +1458,13 @@ impl ICE {
             _ => {}
         }
 
+        // do not report ub that we are not interested in
+        if let Some((_, ICEKind::Ub(ub), _)) = &found_error {
+            if ub.category.is_suppressed() {
+                found_error = None;
+            }
+        }
+
         // unmut
         let found_error = found_error;
 
@@ -1168,6 +1489,7 @@ impl ICE {
                 executable: Executable::Rustc,
                 query_stack,
                 kind: icekind,
+                ..Default::default()
             };
             Some(ice)
         } else {
@@ -1262,6 +1584,7 @@ impl ICE {
                 executable: executable.clone(),
                 query_stack,
                 kind,
+                ..Default::default()
             };
             //  dbg!(&ice);
 
@@ -1275,12 +1598,9 @@ impl ICE {
 
         let mut ret = None;
         if let Some((mut error_reason, ice_kind, query_stack)) = found_error {
-            let ice_kind = if matches!(ice_kind, ICEKind::Ub(..)) {
-                if miri_finding_is_potentially_interesting {
-                    ICEKind::Ub(UbKind::Interesting)
-                } else {
-                    ICEKind::Ub(UbKind::Uninteresting)
-                }
+            let ice_kind = if let ICEKind::Ub(mut ub) = ice_kind {
+                ub.interesting = miri_finding_is_potentially_interesting;
+                ICEKind::Ub(ub)
             } else {
                 ice_kind
             };
@@ -1289,7 +1609,10 @@ impl ICE {
                 // PRECHECK
                 // optimization: check if rustc crashes on the file without needing any flags, if yes, return that as an ICE
                 // we might produce several of those if we check different flags but they should all be deduplicated laster on?
-                let mut pure_rustc_cmd = Command::new(Executable::Rustc.path());
+                let mut pure_rustc_cmd = Command::new(match executable {
+                    Executable::Rustc => exec_path.to_string(),
+                    _ => Executable::Rustc.path(),
+                });
                 pure_rustc_cmd.arg(file);
                 pure_rustc_cmd.current_dir(global_tempdir_path);
 
@@ -1298,7 +1621,7 @@ impl ICE {
 
                 // shitty destructing
 
-                let seconds_elapsed = thread_start.elapsed().as_secs();
+                let seconds_elapsed = seconds_running();
                 if seconds_elapsed > (SECONDS_LIMIT) {
                     print!("\r");
                     println!(
@@ -1337,6 +1660,7 @@ impl ICE {
                         executable: Executable::Rustc,
                         query_stack,
                         kind: icekind,
+                        ..Default::default()
                     };
                     PRINTER.log(PrintMessage::IceFound {
                         ice: ice.to_printable(),
@@ -1349,7 +1673,7 @@ impl ICE {
             // rustc or clippy crashed, we have an ice
             // find out which flags are actually responsible of the many we passed
             // run rustc with the file on several flag combinations, if the first one ICEs, abort
-            let mut bad_flags: Vec<&&str> = Vec::new();
+            let mut bad_flags: Vec<String> = Vec::new();
 
             let args2 = actual_args
                 .iter()
@@ -1386,7 +1710,7 @@ impl ICE {
                     // clippyfix for example needs special handling here
                     let output = if matches!(executable, Executable::ClippyFix) {
                         let (output, _somestr, _flags) = run_clippy_fix_with_args(
-                            &executable.path(),
+                            exec_path,
                             file,
                             &last.iter().map(|x| **x).collect::<Vec<_>>(),
                             global_tempdir_path,
@@ -1408,163 +1732,93 @@ impl ICE {
                     // remove the tempdir
                     tempdir.close().unwrap();
 
-                    // iterate and with each iteration, remove one unneeded flag and check if the ICE still reproduces
+                    // find the smallest set of flags that still reproduces the ICE
                     if found_error2.is_some() {
-                        // remove one flag at a time, but only if ice still reproduces
-                        let mut start_flags: Vec<&&str> =
+                        let mut start_flags: Vec<String> =
                             if matches!(executable, Executable::ClippyFix) {
-                                last.clone()
+                                last.iter().map(|flag| flag.to_string()).collect()
                             } else {
-                                flags_orig.iter().collect::<Vec<_>>()
+                                flags_orig.iter().map(|flag| flag.to_string()).collect()
                             };
                         if !has_main && !start_flags.iter().any(|f| f.contains("crate-type=lib")) {
-                            start_flags.push(&"--crate-type=lib");
+                            start_flags.push(String::from("--crate-type=lib"));
                         }
+                        let start_flags_len = start_flags.len();
 
-                        let mut start_flags_previous_iter = start_flags.clone();
-                        let mut initial = true;
-
-                        // stop if we can't reduce any further
-                        let mut reduced_flags: bool = false;
-
-                        while initial
-                            || (!start_flags.is_empty()) && start_flags != start_flags_previous_iter
-                        {
-                            start_flags_previous_iter.clone_from(&start_flags);
-                            for (i, _f) in start_flags.clone().iter().enumerate() {
-                                //   eprintln!("for loop: START FLAGS {}", start_flags.len());
-                                let mut start_flags_one_removed = start_flags.clone();
-                                // remove one of the flags
-                                let _removed = start_flags_one_removed.remove(i);
-                                // dbg!(removed);
-                                // check if we still ice
-
-                                // TODO tempdiring is broken????
-
-                                let output = if matches!(executable, Executable::ClippyFix) {
-                                    let (output, _somestr, _flags) = run_clippy_fix_with_args(
-                                        &executable.path(),
-                                        file,
-                                        &start_flags_one_removed
-                                            .iter()
-                                            .map(|x| **x)
-                                            .collect::<Vec<_>>(),
-                                        global_tempdir_path,
-                                    )
-                                    .unwrap();
-                                    output
-                                } else {
-                                    // rustc?
-                                    let rustc_flags = start_flags_one_removed
-                                        .iter()
-                                        .filter(|flag| ***flag != "-ocodegen");
-
-                                    let file_content =
-                                        std::fs::read_to_string(file).unwrap_or_default();
-
-                                    // we also need to do all this crap of removing features that are already contained in the file here;
-                                    // do we actually, shouldnt this be done automatically??
-
-                                    // if a file has #![feature(foo)] and we pass "-Zcrate-attr=feature(foo)" in addition to that, that may cause an error
-                                    let regex = Regex::new(r"\[feature\(.*\)\]").unwrap();
-                                    // extract all the used feature gates
-                                    let features_enabled_in_file = regex
-                                        .find_iter(&file_content)
-                                        .map(|x| x.as_str())
-                                        .map(|s| s.strip_prefix("[feature(").unwrap_or(s))
-                                        .map(|s| s.strip_suffix(")]").unwrap_or(s))
-                                        .flat_map(|s| s.split(','))
-                                        .filter(|s| !s.is_empty())
-                                        // remove surrounding whitespaces etc
-                                        .map(|s| s.trim())
-                                        .map(|s| {
-                                            s.trim_matches(|c| ['(', ')', '[', ']'].contains(&c))
-                                        })
-                                        .collect::<Vec<&str>>();
-
-                                    // if a rustc flag specifies a feature that is already contained in the file, skip the rustc flag to avoid duplicate features:
-                                    let rustc_flags = rustc_flags
-                                        .filter(|flag| {
-                                            // no feature flag, keep
-                                            !flag.contains("-Zcrate-attr=feature(")
-                                                ||
-                                                // feature flag, only keep if not specified in file already
-                                                 flag.contains("-Zcrate-attr=feature(")
-                                                    && !features_enabled_in_file
-                                                        .iter()
-                                                        .any(|fif| flag.contains(fif))
-                                        })
-                                        .copied();
-
-                                    // remove -o flags
-                                    let args = rustc_flags.filter(|flag| {
-                                        !(flag.starts_with("-o") || flag.contains("dump-mir-dir"))
-                                    });
-
-                                    let tempdir5 = TempDir::new_in(
-                                        global_tempdir_path,
-                                        "rustc_testrunner_tmpdir",
-                                    )
-                                    .unwrap();
-                                    let tempdir_path = tempdir5.path();
-                                    // do we need ot tempdir outputfile?
-                                    let dump_mir_dir =
-                                        format!("-Zdump-mir-dir={}", tempdir_path.display());
-
-                                    let mut cmd = Command::new(exec_path);
-                                    //                             dbg!(&args);
-                                    cmd.arg(file).args(args).arg(dump_mir_dir);
-                                    cmd.current_dir(tempdir_path);
-                                    //  dbg!(&cmd);
-
-                                    let output = prlimit_run_command(&mut cmd).unwrap();
-                                    tempdir5.close().unwrap();
-                                    output
-                                };
-                                //    std::thread::sleep(std::time::Duration::from_secs(1));
-                                //  dbg!(&output);
-
-                                let found_error3 = find_ICE_string(file, executable, output);
-
-                                // we still have an error, yay
-                                // save the flags with one removed as our new starting point
-                                if found_error3.is_some() {
-                                    assert!(start_flags != start_flags_one_removed);
-                                    start_flags = start_flags_one_removed;
-                                    //       eprintln!("removed {:?}", removed);
-                                    //     eprintln!("new start_flags (flags left) {:?}", &start_flags);
-                                    // restart the loop as we removed a flag
-                                    reduced_flags = true;
-                                    break;
-                                } else {
-                                    // we found nothing, continue
-                                }
-                                //start_flags_last_iter = start_flags.clone();
+                        bad_flags = minimize_flags(start_flags, |flags| {
+                            // past the --time-budget, keep the flags that we have so far
+                            if budget_spent() {
+                                return false;
                             }
-                            initial = false;
-                            // eprintln!("Setting bad_flags == ");
-                            bad_flags = if reduced_flags {
-                                start_flags_previous_iter.clone()
+                            let output = if matches!(executable, Executable::ClippyFix) {
+                                let (output, _somestr, _flags) = run_clippy_fix_with_args(
+                                    exec_path,
+                                    file,
+                                    &flags.iter().map(String::as_str).collect::<Vec<_>>(),
+                                    global_tempdir_path,
+                                )
+                                .unwrap();
+                                output
                             } else {
-                                start_flags.clone()
+                                // rustc?
+                                let rustc_flags = flags.iter().filter(|flag| *flag != "-ocodegen");
+
+                                let file_content =
+                                    std::fs::read_to_string(file).unwrap_or_default();
+
+                                // if a file has #![feature(foo)] and we pass "-Zcrate-attr=feature(foo)" in addition to that, that may cause an error
+                                let regex = Regex::new(r"\[feature\(.*\)\]").unwrap();
+                                // extract all the used feature gates
+                                let features_enabled_in_file = regex
+                                    .find_iter(&file_content)
+                                    .map(|x| x.as_str())
+                                    .map(|s| s.strip_prefix("[feature(").unwrap_or(s))
+                                    .map(|s| s.strip_suffix(")]").unwrap_or(s))
+                                    .flat_map(|s| s.split(','))
+                                    .filter(|s| !s.is_empty())
+                                    // remove surrounding whitespaces etc
+                                    .map(|s| s.trim())
+                                    .map(|s| s.trim_matches(|c| ['(', ')', '[', ']'].contains(&c)))
+                                    .collect::<Vec<&str>>();
+
+                                // if a rustc flag specifies a feature that is already contained in the file, skip the rustc flag to avoid duplicate features:
+                                let rustc_flags = rustc_flags.filter(|flag| {
+                                    // no feature flag, keep
+                                    !flag.contains("-Zcrate-attr=feature(")
+                                        ||
+                                        // feature flag, only keep if not specified in file already
+                                        !features_enabled_in_file
+                                            .iter()
+                                            .any(|fif| flag.contains(fif))
+                                });
+
+                                // remove -o flags
+                                let args = rustc_flags.filter(|flag| {
+                                    !(flag.starts_with("-o") || flag.contains("dump-mir-dir"))
+                                });
+
+                                let tempdir5 =
+                                    TempDir::new_in(global_tempdir_path, "rustc_testrunner_tmpdir")
+                                        .unwrap();
+                                let tempdir_path = tempdir5.path();
+                                let dump_mir_dir =
+                                    format!("-Zdump-mir-dir={}", tempdir_path.display());
+
+                                let mut cmd = Command::new(exec_path);
+                                cmd.arg(file).args(args).arg(dump_mir_dir);
+                                cmd.current_dir(tempdir_path);
+
+                                let output = prlimit_run_command(&mut cmd).unwrap();
+                                tempdir5.close().unwrap();
+                                output
                             };
-                        }
-                        if !reduced_flags {
+
+                            find_ICE_string(file, executable, output).is_some()
+                        });
+
+                        if bad_flags.len() == start_flags_len {
                             eprintln!("FAILED TO REDUCE ANY FLAGS");
                         }
-                        //  bad_flags = start_flags;
-                        /*
-                        // let found_error3 = find_ICE_string(file, executable, output);
-                         // remove the tempdir
-                         //  tempdir.close().unwrap();
-                         let wtf = if found_error3.is_some() {
-                             // save the flags that the ICE repros with
-                             // bad_flags = start_flags.iter().collect::<Vec<_>>();
-                             true
-                         } else {
-                             false
-                         };
-                          */
 
                         // find out if this is a beta/stable/nightly regression
                     } else {
@@ -1576,16 +1830,23 @@ impl ICE {
                         );
                     }
                 }
+                Executable::Miri => {
+                    // keep the flags around so that the finding can be reproduced and reduced later on
+                    bad_flags = compiler_flags
+                        .iter()
+                        .chain(miri_flags.iter())
+                        .map(|flag| flag.to_string())
+                        .collect();
+                }
                 Executable::Clippy
                 | Executable::Rustdoc
                 | Executable::RustAnalyzer
                 | Executable::Rustfmt
-                | Executable::Miri
                 | Executable::Kani
                 | Executable::Marker => {}
             }
 
-            let seconds_elapsed = thread_start.elapsed().as_secs();
+            let seconds_elapsed = seconds_running();
             if seconds_elapsed > (SECONDS_LIMIT * 10) {
                 print!("\r");
                 println!(
@@ -1625,6 +1886,7 @@ impl ICE {
                     executable: executable.clone(),
                     query_stack: Vec::new(),
                     kind: ICEKind::Hang(seconds_elapsed),
+                    ..Default::default()
                 };
                 PRINTER.log(PrintMessage::IceFound {
                     ice: hang.to_printable(),
@@ -1633,11 +1895,15 @@ impl ICE {
                 return Some(hang);
             }
 
-            let regressing_channel =
-                find_out_crashing_channel(&bad_flags, file, global_tempdir_path);
+            // five more runs, not within the --time-budget anymore
+            let regression_detailed = if budget_spent() {
+                None
+            } else {
+                reproduction_matrix(executable, &bad_flags, file, global_tempdir_path)
+            };
             // add these for a more accurate representation of what we ran originally
-            bad_flags.push(&"-ooutputfile");
-            bad_flags.push(&"-Zdump-mir-dir=dir");
+            bad_flags.push(String::from("-ooutputfile"));
+            bad_flags.push(String::from("-Zdump-mir-dir=dir"));
 
             if error_reason.len() > ice_msg.len() {
                 ice_msg = error_reason.clone();
@@ -1646,24 +1912,21 @@ impl ICE {
             }
 
             let ret2 = ICE {
-                regresses_on: match executable {
-                    Executable::Clippy => Regression::Master,
-                    _ => regressing_channel,
-                },
+                regresses_on: regression_detailed
+                    .as_ref()
+                    .map_or(Regression::Master, RegressionDetailed::regression),
 
                 needs_feature: uses_feature,
                 file: file.to_owned(),
-                args: bad_flags
-                    .iter()
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>(),
+                args: bad_flags,
                 // executable: rustc_path.to_string(),
                 error_reason,
                 ice_msg: ice_msg.clone(),
                 executable: executable.clone(),
                 query_stack,
                 kind: ice_kind,
-                //cmd,
+                regression_detailed,
+                ..Default::default() //cmd,
             };
 
             ret = Some(ret2);
@@ -1679,7 +1942,7 @@ impl ICE {
 
         // print a warning if a file takes longer than X to process
         // @TODO this only reports if the file finishes running, if we are stuck, we wont
-        let seconds_elapsed = thread_start.elapsed().as_secs();
+        let seconds_elapsed = seconds_running();
         if seconds_elapsed > (SECONDS_LIMIT * 10) {
             print!("\r");
             println!(
@@ -1720,6 +1983,7 @@ impl ICE {
                 executable: executable.clone(),
                 query_stack: Vec::new(),
                 kind: ICEKind::Hang(seconds_elapsed),
+                ..Default::default()
             };
             ret = Some(ret_hang);
         }
@@ -1734,99 +1998,109 @@ impl ICE {
     }
 }
 
-/// find out if we crash on master, nightly, beta or stable
-fn find_out_crashing_channel(
-    bad_flags: &Vec<&&str>,
+// rustc rejects a flag that this toolchain does not know
+pub(crate) static UNSUPPORTED_FLAG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"error: (unknown (unstable|codegen) option|unrecognized option|incorrect value .* for (unstable|codegen) option|the option `Z` is only accepted on the nightly compiler)",
+    )
+    .unwrap()
+});
+
+/// run the finding with the executable of the toolchain and see what happens
+fn toolchain_outcome(
+    executable: &Executable,
+    toolchain: &str,
+    bad_flags: &[String],
     file: &Path,
     global_tempdir_path: &PathBuf,
-) -> Regression {
-    // simply check if we crash on nightly, beta, stable or master
-    let toolchain_home: PathBuf = {
-        let mut p = home::rustup_home().unwrap();
-        p.push("toolchains");
-        p
+) -> ToolchainOutcome {
+    let Some(exec_path) = executable
+        .path_in_toolchain(toolchain)
+        .filter(|path| path.exists())
+    else {
+        return ToolchainOutcome::NotInstalled;
     };
+    // the toolchains of the matrix have their own miri sysroot
+    if matches!(executable, Executable::Miri) && !miri_setup(toolchain) {
+        return ToolchainOutcome::NotInstalled;
+    }
 
-    let bad_but_no_nightly_flags = bad_flags
-        .iter()
-        .filter(|flag| !flag.starts_with("-Z"))
-        .collect::<Vec<_>>();
-
-    let tempdir = TempDir::new_in(global_tempdir_path, "rustc_testrunner_tmpdir").unwrap();
-    let tempdir_path = tempdir.path();
-    let output_file = format!("-o{}/file1", tempdir_path.display());
-    let dump_mir_dir = format!("-Zdump-mir-dir={}", tempdir_path.display());
-
-    let mut nightly_path = toolchain_home.clone();
-    nightly_path.push("nightly-x86_64-unknown-linux-gnu");
-    nightly_path.push("bin");
-    nightly_path.push("rustc");
-    let mut beta_path = toolchain_home.clone();
-    beta_path.push("beta-x86_64-unknown-linux-gnu");
-    beta_path.push("bin");
-    beta_path.push("rustc");
-    let mut stable_path = toolchain_home;
-    stable_path.push("stable-x86_64-unknown-linux-gnu");
-    stable_path.push("bin");
-    stable_path.push("rustc");
-
-    let stable_ice: bool = find_ICE_string(
-        file,
-        &Executable::Rustc,
-        prlimit_run_command(
-            Command::new(stable_path)
-                .arg(file)
-                .args(&bad_but_no_nightly_flags)
-                .arg(&output_file), //.arg(&dump_mir_dir)
-        )
-        .unwrap(),
-    )
-    .is_some();
-
-    let beta_ice: bool = find_ICE_string(
-        file,
-        &Executable::Rustc,
-        prlimit_run_command(
-            Command::new(beta_path)
-                .arg(file)
-                .args(&bad_but_no_nightly_flags)
-                .arg(&output_file), //.arg(&dump_mir_dir)
-        )
-        .unwrap(),
-    )
-    .is_some();
+    let output = if matches!(executable, Executable::Rustc) {
+        let tempdir = TempDir::new_in(global_tempdir_path, "rustc_testrunner_tmpdir").unwrap();
+        let tempdir_path = tempdir.path();
+        let output_file = format!("-o{}/file1", tempdir_path.display());
+        let dump_mir_dir = format!("-Zdump-mir-dir={}", tempdir_path.display());
 
-    let nightly_ice: bool = find_ICE_string(
-        file,
-        &Executable::Rustc,
         prlimit_run_command(
-            Command::new(nightly_path)
+            Command::new(exec_path)
                 .arg(file)
                 .args(bad_flags)
                 .arg(&output_file)
-                .arg(dump_mir_dir),
+                .arg(dump_mir_dir)
+                .current_dir(tempdir_path)
+                // let stable and beta accept -Z flags and features instead of dropping them
+                .env("RUSTC_BOOTSTRAP", "1"),
         )
-        .unwrap(),
-    )
-    .is_some();
-    // remove tempdir
-    tempdir.close().unwrap();
-
-    if stable_ice {
-        Regression::Stable
-    } else if beta_ice {
-        Regression::Beta
-    } else if nightly_ice {
-        Regression::Nightly
+        .unwrap()
+    } else {
+        // same invocation and scaffold (cargo project for miri, clippy --fix ..) as in discover
+        let (compiler_flags, miri_flags) = split_flags(executable, bad_flags);
+        let (output, _cmd, _actual_args) = run_executable(
+            executable,
+            &exec_path.display().to_string(),
+            file,
+            false,
+            &compiler_flags,
+            &miri_flags,
+            global_tempdir_path,
+        )
+        .unwrap();
+        output
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let success = output.status.success();
+
+    if find_ICE_string(file, executable, output).is_some() {
+        ToolchainOutcome::Ice
+    } else if UNSUPPORTED_FLAG_REGEX.is_match(&stderr) {
+        ToolchainOutcome::FlagsUnsupported
+    } else if success {
+        ToolchainOutcome::Success
     } else {
-        Regression::Master
+        ToolchainOutcome::CompileError
     }
 }
 
+/// find out if the executable crashes on stable, beta, nightly, master and local-debug-assertions,
+/// None if we can only run it from master
+fn reproduction_matrix(
+    executable: &Executable,
+    bad_flags: &[String],
+    file: &Path,
+    global_tempdir_path: &PathBuf,
+) -> Option<RegressionDetailed> {
+    executable.path_in_toolchain("master")?;
+    let host = HOST_TRIPLE.as_str();
+    let outcome = |toolchain: &str| {
+        toolchain_outcome(executable, toolchain, bad_flags, file, global_tempdir_path)
+    };
+
+    Some(RegressionDetailed {
+        host: host.to_string(),
+        stable: outcome(&format!("stable-{host}")),
+        beta: outcome(&format!("beta-{host}")),
+        nightly: outcome(&format!("nightly-{host}")),
+        master: outcome("master"),
+        local_debug_assertions: outcome("local-debug-assertions"),
+    })
+}
+
 static KEYWORDS_MIRI_UB: Lazy<Vec<Regex>> = Lazy::new(|| {
     [
         "error: Undefined Behavior",
-        // "the evaluated program leaked memory", // memleaks are save apparently
+        // memleaks are save apparently, these are --miri-suppress'ed by default
+        "the evaluated program leaked memory",
+        "error: unsupported operation",
         "this indicates a bug in the program",
         "the compiler unexpectedly panicked",
         "thread 'rustc' panicked at",
@@ -2038,7 +2312,11 @@ fn find_ICE_string(
                     //  dbg!(&ub_line);
                     if let Some(ub_line_inner) = ub_line {
                         // this is a return inside the iterator
-                        Some((ub_line_inner, ICEKind::Ub(UbKind::Uninteresting)))
+                        let ub = UbKind::from_miri_output(
+                            &ub_line_inner,
+                            &String::from_utf8_lossy(executable_output),
+                        );
+                        Some((ub_line_inner, ICEKind::Ub(ub)))
                     } else {
                         // we didn't find ub, but perhaps miri crashed?
                         // TRICKY: from just looking at the output, we don't know if it is the program or miri that crashes which is tricky
@@ -2945,9 +3223,8 @@ fn tree_splice_incr_fuzz(global_tempdir_path: &Path) {
     }
 }
 
-const REDUCTION_DIR: &str = "icemaker_reduced";
-
-fn reduce_all(global_tempdir_path: &Path) {
+/// reduce all findings of the errors.json in the cwd, `jobs` at a time
+fn reduce_all(global_tempdir_path: &Path, jobs: usize) {
     // todo handle all Executables
 
     // reduce code using $Executable,
@@ -2969,24 +3246,7 @@ fn reduce_all(global_tempdir_path: &Path) {
     let root_path = std::env::current_dir().expect("no cwd!");
     // parse the reported ICEs
     let errors_json = root_path.join("errors.json");
-    let ices: Vec<ICE> = if errors_json.exists() {
-        let read = match std::fs::read_to_string(&errors_json) {
-            Ok(content) => content,
-            Err(_) => panic!("failed to read '{}'", errors_json.display()),
-        };
-        match serde_json::from_str(&read) {
-            Ok(previous_errors) => previous_errors,
-            Err(e) => {
-                // this can happen if we for example change the representation of Ice so that that the previous file is no longer compatible with the new format
-                eprintln!("Failed to parse errors.json, is it a json file?");
-                eprintln!("original error: '{e:?}'");
-                Vec::new()
-            }
-        }
-    } else {
-        // we don't have a file, start blank
-        Vec::new()
-    };
+    let ices: Vec<ICE> = read_errors_json(&errors_json);
 
     std::fs::create_dir_all(REDUCTION_DIR).expect("could not create './icemaker_reduced/' dir");
 
@@ -3000,237 +3260,10 @@ fn reduce_all(global_tempdir_path: &Path) {
         .is_some(); // must not be empty..
     */
 
-    ices.into_iter().for_each(|ice| {
-        reduce_ice_code(ice, global_tempdir_path);
-    })
-}
-
-// minimize ICE code
-pub(crate) fn reduce_ice_code(ice: ICE, global_tempdir_path: &Path) {
-    let reduction_start_time = Instant::now();
-
-    let file = &ice.file;
-    // if we run inside a tempdir, we need an absolute path, because the file is not copied into the tempdir
-    let file = &file.canonicalize().expect("file canonicalizsation failed");
-    let flags = &ice.args;
-    let executable = &ice.executable;
-    let bin = executable.path();
-    let kind = ice.kind.clone();
-
-    let tempdir = TempDir::new_in(global_tempdir_path, "icemaker_reducing_tempdir").unwrap();
-    let tempdir_path = tempdir.path();
-
-    if matches!(executable, Executable::Rustc)
-        && matches!(kind, ICEKind::Ice(_))
-        &&
-    // skip OOMs which treereduce cant really handle
-    ! ice.error_reason.contains("allocating stack failed")
-    {
-        eprintln!("{}", ice.to_printable());
-
-        /*  eprintln!("------------------original");
-        eprintln!(
-            "{}",
-            std::fs::read_to_string(file).unwrap_or("FAILURE TO READ ICE FILE".into())
-        );
-        */
-
-        let mut trd = std::process::Command::new("prlimit");
-        trd.arg(format!("--as={}", 3076_u32 * 1000_u32 * 1000_u32)) // 3 gb of ram
-            .arg("--cpu=120") //  2 mins
-            .arg("treereduce-rust");
-        trd.args([
-            "--quiet",
-            "--passes=10",
-            "--min-reduction=10",
-            "--interesting-exit-code=101",
-            "--on-parse-error",
-            "ignore",
-            "--output", // output to stdout
-            "-",
-        ]);
-
-        trd.arg("--source");
-        trd.arg(file);
-
-        trd.arg("--");
-        // we also need to run the rustc that treereduce-rust launches inside prlimit to not blow up the system
-
-        trd.args([
-            "prlimit",
-            "--noheadings",
-            &format!("--as={}", 3076_u32 * 1000_u32 * 1000_u32),
-            "--cpu=60",
-        ]);
-        trd.arg(&bin);
-
-        if !flags.is_empty() {
-            trd.args(flags);
-        }
-        trd.arg("@@.rs");
-        trd.current_dir(tempdir_path);
-        let output = trd.output().unwrap();
-        let reduced_file = String::from_utf8_lossy(&output.stdout).to_string();
-        let reduced_file_clone = reduced_file.clone();
-        /*
-              eprintln!("---------------------------reduced");
-                    eprintln!("{reduced_file}");
-
-                    eprintln!("---------------------------");
-        */
-        // find possible edition flags inside the rustcflags which we will also need to pass to rustfmt?
-        let mut fmt = std::process::Command::new("rustfmt")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .current_dir(tempdir_path)
-            .arg("--edition=2021")
-            .spawn()
-            .expect("Failed to spawn rustfmt process");
-
-        let mut stdin = fmt.stdin.take().expect("Failed to open stdin");
-        std::thread::spawn(move || {
-            stdin
-                .write_all(reduced_file_clone.as_bytes())
-                .expect("Failed to write to stdin");
-        });
-
-        let output = fmt.wait_with_output().expect("Failed to read stdout");
-
-        // if rustfmt failed, save the original file
-        let reduced_fmt_file = if output.status.success() {
-            String::from_utf8_lossy(&output.stdout).to_string()
-        } else {
-            reduced_file
-        };
-
-        let analysis = Analysis {
-            ice: ice.clone(),
-            mvce: reduced_fmt_file,
-        };
-        let seconds_elapsed = reduction_start_time.elapsed().as_secs();
-
-        eprintln!("reduction took {seconds_elapsed} seconds...........formatted:");
-        eprintln!("{}", analysis.mvce);
-        eprintln!("\n\n\n");
-        // write reduced file to disk
-        let dir = PathBuf::from(REDUCTION_DIR);
-        // REDUCTION_DIR/filename.rs
-        // @TODO do not overwrite already reduced files here if flags are different
-        let path_reduced_file: PathBuf =
-            dir.join(file.file_name().expect("could not get filename"));
-        std::fs::write(path_reduced_file, analysis.mvce).expect("could not write file content");
-    }
-}
-
-pub(crate) fn reduce_ice_code_to_string(ice: ICE, global_tempdir_path: &Path) -> String {
-    let file = &ice.file;
-    // if we run inside a tempdir, we need an absolute path, because the file is not copied into the tempdir
-    let file = &file.canonicalize().expect("file canonicalizsation failed");
-    let flags = &ice.args;
-    let executable = &ice.executable;
-    let bin = executable.path();
-    let kind = ice.kind.clone();
-
-    let tempdir = TempDir::new_in(global_tempdir_path, "icemaker_reducing_tempdir").unwrap();
-    let tempdir_path = tempdir.path();
-
-    if matches!(executable, Executable::Rustc | Executable::RustAnalyzer)
-        && matches!(kind, ICEKind::Ice(_))
-        || matches!(kind, ICEKind::DoubleIce)
-        &&
-    // skip OOMs which treereduce cant really handle
-    ! ice.error_reason.contains("allocating stack failed")
-    {
-        eprintln!("{}", ice.to_printable());
+    let ices = reduce_findings(ices, global_tempdir_path, jobs);
 
-        /*  eprintln!("------------------original");
-        eprintln!(
-            "{}",
-            std::fs::read_to_string(file).unwrap_or("FAILURE TO READ ICE FILE".into())
-        );
-        */
-
-        let mut trd = std::process::Command::new("prlimit");
-        trd.arg(format!("--as={}", 3076_u32 * 1000_u32 * 1000_u32)) // 3 gb of ram
-            .arg("--cpu=120") //  2 mins
-            .arg("treereduce-rust");
-        trd.args([
-            "--quiet",
-            "--passes=10",
-            "--min-reduction=10",
-            "--interesting-exit-code=101",
-            "--on-parse-error",
-            "ignore",
-            "--output", // output to stdout
-            "-",
-        ]);
-
-        trd.arg("--source");
-        trd.arg(file);
-
-        trd.arg("--");
-        // we also need to run the rustc that treereduce-rust launches inside prlimit to not blow up the system
-
-        trd.args([
-            "prlimit",
-            "--noheadings",
-            &format!("--as={}", 3076_u32 * 1000_u32 * 1000_u32),
-            "--cpu=60",
-        ]);
-        trd.arg(&bin);
-        if executable == &Executable::RustAnalyzer {
-            // THIS NEEDS TO BE IN SYNC WITH run_rust_analyzer() arg!!
-            trd.arg("highlight");
-        } else {
-            if !flags.is_empty() {
-                trd.args(flags);
-            }
-            trd.arg("@@.rs");
-        }
-        trd.current_dir(tempdir_path);
-
-        let output = trd.output().unwrap();
-        let reduced_file = String::from_utf8_lossy(&output.stdout).to_string();
-        let reduced_file_clone = reduced_file.clone();
-        /*
-              eprintln!("---------------------------reduced");
-                    eprintln!("{reduced_file}");
-
-                    eprintln!("---------------------------");
-        */
-        // find possible edition flags inside the rustcflags which we will also need to pass to rustfmt?
-        let mut fmt = std::process::Command::new("rustfmt")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .current_dir(tempdir_path)
-            .arg("--edition=2021")
-            .spawn()
-            .expect("Failed to spawn rustfmt process");
-
-        let mut stdin = fmt.stdin.take().expect("Failed to open stdin");
-        std::thread::spawn(move || {
-            stdin
-                .write_all(reduced_file_clone.as_bytes())
-                .expect("Failed to write to stdin");
-        });
-
-        let output = fmt.wait_with_output().expect("Failed to read stdout");
-
-        // if rustfmt failed, save the original file
-        let reduced_fmt_file = if output.status.success() {
-            String::from_utf8_lossy(&output.stdout).to_string()
-        } else {
-            reduced_file
-        };
-
-        return reduced_fmt_file;
-    }
-    String::from("ERROR in icemaker while reducing file with treereduce")
-}
-
-#[derive(Debug, Clone)]
-struct Analysis {
-    #[allow(unused)]
-    ice: ICE,
-    mvce: String,
+    // remember which findings are ice-on-valid
+    let errors_new = serde_json::to_string_pretty(&ices).expect("failed to jsonify errors");
+    std::fs::write(&errors_json, errors_new)
+        .unwrap_or_else(|_| panic!("error: failed to write to {}", errors_json.display()));
 }
@@ -0,0 +1,333 @@
+// keep the memory of all tool processes that run at the same time within a budget, see
+// --memory-budget
+//
+// rayon decides how many files are checked at once, but every one of them may start a rustc that
+// is allowed to use 3 GB while most of them need a fraction of that. Before a process is started it
+// reserves what processes of the same program needed at most recently (3 GB until we have measured
+// one) and waits until the running processes leave room for it. While a process runs the memory of
+// it and its child processes (cargo miri runs miri in a child) is sampled from /proc, a process that
+// grows beyond its reservation counts with what it really uses.
+//
+// a reduction reserves memory for all the oracles that treereduce runs at once, the oracle processes
+// (icemaker --interesting) do not limit themselves. Time spent waiting for memory does not count
+// towards hangs, see waited_for_memory().
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::{Child, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use once_cell::sync::Lazy;
+
+use crate::library::Args;
+use crate::run_commands::{mem_available_kb, MEMORY_PER_RUN_KB};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+// an estimate moves this part of the way down to a smaller measurement
+const ESTIMATE_DECAY: u64 = 8;
+
+thread_local! {
+    // how long the admissions of this thread blocked so far
+    static WAITED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+// defaults to the memory that is available when we start
+pub(crate) static MEMORY_LIMITER: Lazy<MemoryLimiter> = Lazy::new(|| {
+    let args = Args::parse();
+    if args.interesting.is_some() {
+        // the reduction that runs us already reserved the memory
+        return MemoryLimiter::new(u64::MAX);
+    }
+    let budget_kb = args
+        .memory_budget
+        .map(|gb| gb * 1000 * 1000)
+        .or_else(mem_available_kb)
+        .unwrap_or(MEMORY_PER_RUN_KB);
+    MemoryLimiter::new(budget_kb)
+});
+
+#[derive(Debug)]
+struct Running {
+    reserved_kb: u64,
+    // peak resident memory so far
+    measured_kb: u64,
+}
+
+impl Running {
+    fn in_use_kb(&self) -> u64 {
+        self.reserved_kb.max(self.measured_kb)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    running: HashMap<u64, Running>,
+    next_id: u64,
+    // estimated peak memory per program
+    estimates_kb: HashMap<String, u64>,
+}
+
+impl State {
+    fn in_use_kb(&self) -> u64 {
+        self.running.values().map(Running::in_use_kb).sum()
+    }
+
+    fn estimate_kb(&self, program: &str) -> u64 {
+        self.estimates_kb
+            .get(program)
+            .copied()
+            .unwrap_or(MEMORY_PER_RUN_KB)
+    }
+
+    /// a single process is always admitted even if it does not fit, nothing would run otherwise
+    fn admits(&self, estimate_kb: u64, budget_kb: u64) -> bool {
+        self.running.is_empty() || self.in_use_kb() + estimate_kb <= budget_kb
+    }
+
+    /// the first measurement replaces the default. A bigger process raises the estimate right
+    /// away, smaller ones lower it slowly so that a single huge file does not throttle the rest of
+    /// the run
+    fn learn(&mut self, program: &str, peak_kb: u64) {
+        if peak_kb == 0 {
+            // the process was gone before we could sample it
+            return;
+        }
+        self.estimates_kb
+            .entry(program.to_string())
+            .and_modify(|estimate_kb| {
+                *estimate_kb = if peak_kb >= *estimate_kb {
+                    peak_kb
+                } else {
+                    *estimate_kb - (*estimate_kb - peak_kb) / ESTIMATE_DECAY
+                }
+            })
+            .or_insert(peak_kb);
+    }
+}
+
+/// how long the admissions of the current thread had to wait for memory so far. A run that is slow
+/// may have been waiting instead of hanging
+pub(crate) fn waited_for_memory() -> Duration {
+    WAITED.with(Cell::get)
+}
+
+#[derive(Debug)]
+pub(crate) struct MemoryLimiter {
+    budget_kb: u64,
+    state: Mutex<State>,
+    freed: Condvar,
+}
+
+impl MemoryLimiter {
+    fn new(budget_kb: u64) -> Self {
+        MemoryLimiter {
+            budget_kb,
+            state: Mutex::new(State::default()),
+            freed: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn budget_kb(&self) -> u64 {
+        self.budget_kb
+    }
+
+    /// blocks until a process of `program` fits into the budget
+    pub(crate) fn admit(&self, program: &OsStr) -> Admission<'_> {
+        self.admit_processes(program, 1)
+    }
+
+    /// blocks until `processes` processes of `program` that run at the same time fit into the budget
+    pub(crate) fn admit_processes(&self, program: &OsStr, processes: u64) -> Admission<'_> {
+        let program = Path::new(program)
+            .file_name()
+            .unwrap_or(program)
+            .to_string_lossy()
+            .to_string();
+        let start = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let estimate_kb = state.estimate_kb(&program) * processes;
+            if state.admits(estimate_kb, self.budget_kb) {
+                WAITED.with(|waited| waited.set(waited.get() + start.elapsed()));
+                let id = state.next_id;
+                state.next_id += 1;
+                state.running.insert(
+                    id,
+                    Running {
+                        reserved_kb: estimate_kb,
+                        measured_kb: 0,
+                    },
+                );
+                return Admission {
+                    limiter: self,
+                    id,
+                    program,
+                };
+            }
+            state = self.freed.wait(state).unwrap();
+        }
+    }
+}
+
+/// a running process, gives its memory back to the limiter when dropped
+pub(crate) struct Admission<'a> {
+    limiter: &'a MemoryLimiter,
+    id: u64,
+    program: String,
+}
+
+impl Admission<'_> {
+    fn measured(&self, kb: u64) {
+        let mut state = self.limiter.state.lock().unwrap();
+        if let Some(running) = state.running.get_mut(&self.id) {
+            running.measured_kb = running.measured_kb.max(kb);
+        }
+    }
+
+    /// wait for the child and sample the memory of it and its children until it exits
+    pub(crate) fn wait_with_output(&self, child: Child) -> std::io::Result<Output> {
+        let pid = child.id();
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    if let Some(kb) = tree_peak_rss_kb(pid) {
+                        self.measured(kb);
+                    }
+                    std::thread::sleep(SAMPLE_INTERVAL);
+                }
+            });
+            let output = child.wait_with_output();
+            done.store(true, Ordering::Relaxed);
+            output
+        })
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap();
+        if let Some(running) = state.running.remove(&self.id) {
+            state.learn(&self.program, running.measured_kb);
+        }
+        self.limiter.freed.notify_all();
+    }
+}
+
+/// VmHWM of the process and its descendants that are still running, None once the process is gone
+fn tree_peak_rss_kb(pid: u32) -> Option<u64> {
+    let own_kb = peak_rss_kb(&format!("/proc/{pid}/status"))?;
+    Some(
+        own_kb
+            + children(pid)
+                .into_iter()
+                .filter_map(tree_peak_rss_kb)
+                .sum::<u64>(),
+    )
+}
+
+/// the child processes that any of the threads of the process started
+fn children(pid: u32) -> Vec<u32> {
+    std::fs::read_dir(format!("/proc/{pid}/task"))
+        .into_iter()
+        .flatten()
+        .filter_map(|task| task.ok())
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .filter_map(|pid| pid.parse::<u32>().ok())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
+
+/// VmHWM of a /proc/<pid>/status, None once the process is gone
+fn peak_rss_kb(status: &str) -> Option<u64> {
+    std::fs::read_to_string(status)
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admission() {
+        let mut state = State::default();
+        // nothing running, always admitted
+        assert!(state.admits(10, 5));
+        state.running.insert(
+            0,
+            Running {
+                reserved_kb: 3,
+                measured_kb: 0,
+            },
+        );
+        assert!(state.admits(2, 5));
+        assert!(!state.admits(3, 5));
+        // grew beyond its reservation
+        state.running.get_mut(&0).unwrap().measured_kb = 4;
+        assert!(!state.admits(2, 5));
+    }
+
+    #[test]
+    fn estimates() {
+        let mut state = State::default();
+        assert_eq!(state.estimate_kb("rustc"), MEMORY_PER_RUN_KB);
+        state.learn("rustc", 0);
+        assert_eq!(state.estimate_kb("rustc"), MEMORY_PER_RUN_KB);
+        state.learn("rustc", 400);
+        assert_eq!(state.estimate_kb("rustc"), 400);
+        state.learn("rustc", 800);
+        assert_eq!(state.estimate_kb("rustc"), 800);
+        // smaller ones lower it slowly
+        state.learn("rustc", 160);
+        assert_eq!(state.estimate_kb("rustc"), 720);
+        for _ in 0..50 {
+            state.learn("rustc", 160);
+        }
+        assert!(state.estimate_kb("rustc") < 170);
+    }
+
+    #[test]
+    fn own_wait() {
+        let limiter = MemoryLimiter::new(5);
+        let running = limiter.admit(OsStr::new("rustc"));
+        std::thread::scope(|scope| {
+            let waiting = scope.spawn(|| {
+                let _admission = limiter.admit(OsStr::new("rustc"));
+                waited_for_memory()
+            });
+            std::thread::sleep(Duration::from_millis(50));
+            drop(running);
+            assert!(waiting.join().unwrap() >= Duration::from_millis(50));
+        });
+        // this thread did not wait, the other one did
+        assert!(waited_for_memory() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn own_process_tree() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let own_kb = peak_rss_kb(&format!("/proc/{}/status", std::process::id())).unwrap();
+        assert!(children(std::process::id()).contains(&child.id()));
+        assert!(tree_peak_rss_kb(std::process::id()).unwrap() > own_kb);
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
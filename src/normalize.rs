@@ -0,0 +1,468 @@
+// make a reduced file look like something a human would post upstream
+//
+// the reducer only removes code, afterwards we move feature flags into the file, strip comments
+// and lint attributes, collapse nested blocks and rename items to short names (S, T, f).
+// A step is only kept if the finding still reproduces after it.
+
+use std::collections::{HashMap, HashSet};
+
+use tree_sitter::{Node, Parser, Tree};
+
+use crate::flag_minimizer::merge_feature_flags;
+use crate::ice::ICE;
+
+// declarations whose name is not a reference to an item
+const DECLARATION_KINDS: &[&str] = &[
+    "function_item",
+    "function_signature_item",
+    "struct_item",
+    "enum_item",
+    "union_item",
+    "trait_item",
+    "type_item",
+    "associated_type",
+    "enum_variant",
+    "field_declaration",
+    "const_item",
+    "static_item",
+    "mod_item",
+    "macro_definition",
+];
+
+// a plain identifier in here binds a new name
+const PATTERN_KINDS: &[&str] = &[
+    "let_declaration",
+    "parameter",
+    "closure_parameters",
+    "for_expression",
+    "let_condition",
+    "match_pattern",
+    "tuple_pattern",
+    "slice_pattern",
+    "or_pattern",
+    "ref_pattern",
+    "mut_pattern",
+    "reference_pattern",
+    "captured_pattern",
+    "field_pattern",
+];
+
+// generic parameters shadow items of the same name
+const TYPE_PARAMETER_KINDS: &[&str] = &[
+    "type_parameters",
+    "constrained_type_parameter",
+    "optional_type_parameter",
+];
+
+// attributes that hardly ever matter for a crash
+const DEAD_ATTRIBUTES: &[&str] = &[
+    "allow",
+    "warn",
+    "deny",
+    "forbid",
+    "expect",
+    "doc",
+    "must_use",
+    "rustfmt::skip",
+];
+
+type Step = fn(&str) -> Option<String>;
+
+static STEPS: &[Step] = &[
+    strip_comments,
+    remove_dead_attributes,
+    collapse_blocks,
+    rename_types,
+    rename_traits,
+    rename_functions,
+];
+
+fn parse(code: &str) -> Option<Tree> {
+    let mut parser = Parser::new();
+    parser.set_timeout_micros(10_000_000);
+    parser.set_language(&tree_sitter_rust::language()).ok()?;
+    parser.parse(code, None)
+}
+
+fn text<'a>(node: &Node<'_>, code: &'a str) -> &'a str {
+    &code[node.start_byte()..node.end_byte()]
+}
+
+fn visit<'t>(node: Node<'t>, f: &mut impl FnMut(Node<'t>)) {
+    f(node);
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        visit(child, f);
+    }
+}
+
+/// replace the byte ranges, overlapping replacements after the first one are dropped
+fn replace(code: &str, mut replacements: Vec<(usize, usize, String)>) -> Option<String> {
+    if replacements.is_empty() {
+        return None;
+    }
+    replacements.sort_by_key(|(start, _, _)| *start);
+    let mut result = String::new();
+    let mut pos = 0;
+    for (start, end, replacement) in replacements {
+        if start < pos {
+            continue;
+        }
+        result.push_str(&code[pos..start]);
+        result.push_str(&replacement);
+        pos = end;
+    }
+    result.push_str(&code[pos..]);
+    Some(result)
+}
+
+fn strip_comments(code: &str) -> Option<String> {
+    let tree = parse(code)?;
+    let mut removals = Vec::new();
+    visit(tree.root_node(), &mut |node| {
+        if matches!(node.kind(), "line_comment" | "block_comment") {
+            removals.push((node.start_byte(), node.end_byte(), String::new()));
+        }
+    });
+    replace(code, removals)
+}
+
+/// #[allow(dead_code)] => allow, #![rustfmt::skip] => rustfmt::skip
+fn attribute_name(attribute: &str) -> &str {
+    attribute
+        .trim_start_matches('#')
+        .trim_start_matches('!')
+        .trim_start_matches('[')
+        .split(['(', '=', ']'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+}
+
+fn remove_dead_attributes(code: &str) -> Option<String> {
+    let tree = parse(code)?;
+    let mut removals = Vec::new();
+    visit(tree.root_node(), &mut |node| {
+        // tree-sitter-rust takes a #![..] in the first line for a shebang
+        let attribute = matches!(node.kind(), "attribute_item" | "inner_attribute_item")
+            || node.kind() == "shebang" && text(&node, code).starts_with("#![");
+        if attribute && DEAD_ATTRIBUTES.contains(&attribute_name(text(&node, code))) {
+            removals.push((node.start_byte(), node.end_byte(), String::new()));
+        }
+    });
+    replace(code, removals)
+}
+
+/// `{ { foo } }` => `{ foo }`
+fn collapse_blocks(code: &str) -> Option<String> {
+    let mut code = code.to_string();
+    let mut changed = false;
+    // nested blocks overlap, collapse one level per round
+    loop {
+        let tree = parse(&code)?;
+        let mut collapses = Vec::new();
+        visit(tree.root_node(), &mut |node| {
+            if node.kind() != "block" || node.named_child_count() != 1 {
+                return;
+            }
+            let Some(mut inner) = node.named_child(0) else {
+                return;
+            };
+            // a block in statement position is wrapped, `{ { foo }; }` has the type () though
+            if inner.kind() == "expression_statement" && !text(&inner, &code).ends_with(';') {
+                match inner.named_child(0) {
+                    Some(expression) => inner = expression,
+                    None => return,
+                }
+            }
+            let labeled = inner
+                .named_child(0)
+                .is_some_and(|child| child.kind() == "label");
+            if inner.kind() == "block" && !labeled {
+                collapses.push((
+                    node.start_byte(),
+                    node.end_byte(),
+                    text(&inner, &code).to_string(),
+                ));
+            }
+        });
+        match replace(&code, collapses) {
+            Some(collapsed) => {
+                code = collapsed;
+                changed = true;
+            }
+            None => break,
+        }
+    }
+    changed.then_some(code)
+}
+
+/// S, S1, S2 .. that are not used anywhere in the file yet
+fn fresh_names<'a>(base: &'a str, taken: &'a HashSet<String>) -> impl Iterator<Item = String> + 'a {
+    std::iter::once(base.to_string())
+        .chain((1..).map(move |n| format!("{base}{n}")))
+        .filter(|name| !taken.contains(name))
+}
+
+/// items at the top level of the file or of a module, nested items may be shadowed
+fn is_module_level(node: &Node<'_>) -> bool {
+    node.parent().is_some_and(|parent| {
+        parent.kind() == "source_file"
+            || parent.kind() == "declaration_list"
+                && parent.parent().is_some_and(|p| p.kind() == "mod_item")
+    })
+}
+
+/// the identifier binds a local, a parameter or a generic parameter
+fn binds_name(node: &Node<'_>) -> bool {
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    match parent.kind() {
+        // `let x: Foo`, `T: Foo`
+        "let_declaration" | "parameter" | "for_expression" | "let_condition" => {
+            parent.child_by_field_name("pattern") == Some(*node)
+        }
+        "constrained_type_parameter" => parent.child_by_field_name("left") == Some(*node),
+        "tuple_struct_pattern" => parent.child_by_field_name("type") != Some(*node),
+        kind => PATTERN_KINDS.contains(&kind) || TYPE_PARAMETER_KINDS.contains(&kind),
+    }
+}
+
+/// can the identifier refer to an item that is declared in the file?
+fn refers_to_item(node: &Node<'_>, code: &str) -> bool {
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    if DECLARATION_KINDS.contains(&parent.kind())
+        && parent.child_by_field_name("name") == Some(*node)
+    {
+        // fields, variants, methods, associated types..
+        return false;
+    }
+    if matches!(
+        parent.kind(),
+        "scoped_identifier" | "scoped_type_identifier"
+    ) && parent.child_by_field_name("name") == Some(*node)
+    {
+        // other::S is not our S, crate::S is
+        return parent
+            .child_by_field_name("path")
+            .is_some_and(|path| matches!(text(&path, code), "crate" | "self" | "super"));
+    }
+    // x.foo inside of a macro call
+    !(parent.kind() == "token_tree" && node.prev_sibling().is_some_and(|prev| prev.kind() == "."))
+}
+
+/// give the module level items that `declares` returns short names and rename the references to
+/// them. Names that are also used for locals or generic parameters are left alone
+fn rename(code: &str, base: &str, declares: fn(&Node<'_>) -> bool) -> Option<String> {
+    let tree = parse(code)?;
+
+    let mut declared = Vec::new();
+    let mut declaration_names = Vec::new();
+    let mut identifiers = Vec::new();
+    let mut shadowed = HashSet::new();
+    visit(tree.root_node(), &mut |node| {
+        if declares(&node) && is_module_level(&node) {
+            if let Some(name) = node.child_by_field_name("name") {
+                declaration_names.push(name);
+                let name = text(&name, code).to_string();
+                if !declared.contains(&name) {
+                    declared.push(name);
+                }
+            }
+        }
+        if matches!(node.kind(), "identifier" | "type_identifier") {
+            identifiers.push(node);
+            if binds_name(&node) {
+                shadowed.insert(text(&node, code).to_string());
+            }
+        }
+    });
+    // already short enough
+    declared.retain(|name| name.len() > 2 && name != "main" && !shadowed.contains(name));
+
+    let taken = identifiers
+        .iter()
+        .map(|node| text(node, code).to_string())
+        .collect::<HashSet<String>>();
+    let names = declared
+        .into_iter()
+        .zip(fresh_names(base, &taken))
+        .collect::<HashMap<String, String>>();
+
+    let renames = identifiers
+        .iter()
+        .filter(|node| declaration_names.contains(node) || refers_to_item(node, code))
+        .filter_map(|node| {
+            names
+                .get(text(node, code))
+                .map(|new| (node.start_byte(), node.end_byte(), new.clone()))
+        })
+        .collect();
+    replace(code, renames)
+}
+
+fn rename_types(code: &str) -> Option<String> {
+    rename(code, "S", |node| {
+        matches!(
+            node.kind(),
+            "struct_item" | "enum_item" | "union_item" | "type_item"
+        )
+    })
+}
+
+fn rename_traits(code: &str) -> Option<String> {
+    rename(code, "T", |node| node.kind() == "trait_item")
+}
+
+fn rename_functions(code: &str) -> Option<String> {
+    // methods are often referred to by the trait they implement, only rename free functions, see
+    // is_module_level
+    rename(code, "f", |node| node.kind() == "function_item")
+}
+
+/// normalize a reduced file, `still_reproduces` is the oracle that decides whether we keep a step.
+/// Feature flags of the finding become #![feature] attributes, later steps test against the
+/// finding without these flags, which is returned along with the normalized code
+pub(crate) fn normalize_mvce(
+    mvce: String,
+    ice: &ICE,
+    still_reproduces: impl Fn(&str, &ICE) -> bool,
+) -> (String, ICE) {
+    let mut mvce = mvce;
+    let mut ice = ice.clone();
+
+    let (with_features, args) = merge_feature_flags(&mvce, &ice.args);
+    if args != ice.args {
+        let without_flags = ICE {
+            args,
+            ..ice.clone()
+        };
+        if still_reproduces(&with_features, &without_flags) {
+            mvce = with_features;
+            ice = without_flags;
+        }
+    }
+
+    for step in STEPS {
+        let Some(candidate) = step(&mvce) else {
+            continue;
+        };
+        // otherwise roll the step back
+        if candidate != mvce && still_reproduces(&candidate, &ice) {
+            mvce = candidate;
+        }
+    }
+    (mvce, ice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(attribute_name("#[allow(dead_code)]"), "allow");
+        assert_eq!(attribute_name("#![rustfmt::skip]"), "rustfmt::skip");
+        assert_eq!(attribute_name("#[doc = \"foo\"]"), "doc");
+
+        let taken = ["S", "S2"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            fresh_names("S", &taken).take(3).collect::<Vec<_>>(),
+            ["S1", "S3", "S4"]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            strip_comments("// foo\nfn main() { /* bar */ let s = \"// baz\"; }").as_deref(),
+            Some("\nfn main() {  let s = \"// baz\"; }")
+        );
+        assert_eq!(strip_comments("fn main() {}"), None);
+    }
+
+    #[test]
+    fn dead_attributes() {
+        assert_eq!(
+            remove_dead_attributes("#![allow(unused)]\n#[inline]\n#[must_use]\nfn f() {}")
+                .as_deref(),
+            Some("\n#[inline]\n\nfn f() {}")
+        );
+        assert_eq!(remove_dead_attributes("#[inline]\nfn f() {}"), None);
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            collapse_blocks("fn f() { { { g() } } }").as_deref(),
+            Some("fn f() { g() }")
+        );
+        // () instead of the value of g()
+        assert_eq!(collapse_blocks("fn f() { { g() }; }"), None);
+        assert_eq!(collapse_blocks("fn f() { 'a: { break 'a; } }"), None);
+        assert_eq!(collapse_blocks("fn f() { unsafe { g() } }"), None);
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            rename_types(
+                "struct Foo(u8);\nimpl Foo { fn new() -> Foo { Foo(0) } }\n\
+                 fn main() { let x: Foo = Foo::new(); let Foo(y) = x; other::Foo::g(); }"
+            )
+            .as_deref(),
+            Some(
+                "struct S(u8);\nimpl S { fn new() -> S { S(0) } }\n\
+                 fn main() { let x: S = S::new(); let S(y) = x; other::Foo::g(); }"
+            )
+        );
+        // the associated type of Iterator and the generic parameter stay
+        assert_eq!(
+            rename_types(
+                "struct Iter;\nimpl Iterator for Iter { type Item = u8; }\n\
+                 struct Wrapper<Item>(Item);"
+            )
+            .as_deref(),
+            Some("struct S;\nimpl Iterator for S { type Item = u8; }\nstruct S1<Item>(Item);")
+        );
+        assert_eq!(rename_types("fn f<Foo>() {}\nstruct Foo;"), None);
+    }
+
+    #[test]
+    fn traits() {
+        assert_eq!(
+            rename_traits(
+                "trait Show { fn show(&self); }\nimpl Show for u8 { fn show(&self) {} }\n\
+                 fn f<X: Show>(x: X) { x.show(); <u8 as Show>::show(&0); Show::show(&x); }"
+            )
+            .as_deref(),
+            Some(
+                "trait T { fn show(&self); }\nimpl T for u8 { fn show(&self) {} }\n\
+                 fn f<X: T>(x: X) { x.show(); <u8 as T>::show(&0); T::show(&x); }"
+            )
+        );
+    }
+
+    #[test]
+    fn functions() {
+        // methods, fields and paths into other modules keep their names
+        assert_eq!(
+            rename_functions(
+                "fn foo() -> u8 { 0 }\nstruct W { foo: u8 }\nimpl W { fn foo(&self) {} }\n\
+                 fn main() { let w = W { foo: foo() }; w.foo(); W::foo(&w); \
+                 std::foo(); crate::foo(); println!(\"{}\", foo()); }"
+            )
+            .as_deref(),
+            Some(
+                "fn f() -> u8 { 0 }\nstruct W { foo: u8 }\nimpl W { fn foo(&self) {} }\n\
+                 fn main() { let w = W { foo: f() }; w.foo(); W::foo(&w); \
+                 std::foo(); crate::f(); println!(\"{}\", f()); }"
+            )
+        );
+        // a local of the same name would be renamed as well
+        assert_eq!(rename_functions("fn foo() {}\nfn g(foo: u8) {}"), None);
+    }
+}
@@ -13,6 +13,12 @@ pub(crate) enum PrintMessage {
     IceFound {
         ice: ICEDisplay,
     },
+    // --reduce
+    Reducing {
+        index: usize,
+        total: usize,
+        file_name: String,
+    },
 }
 
 pub(crate) struct Printer {
@@ -39,7 +45,7 @@ impl Printer {
         match (prev, &new) {
             // displays "%perc Checking $file ..."
             (
-                PrintMessage::Progress { .. },
+                PrintMessage::Progress { .. } | PrintMessage::Reducing { .. },
                 PrintMessage::Progress {
                     index,
                     total_number_of_files,
@@ -57,9 +63,24 @@ impl Printer {
             (PrintMessage::IceFound { .. }, PrintMessage::IceFound { ref ice }) => {
                 println!("{ice}");
             }
-            (PrintMessage::Progress { .. }, PrintMessage::IceFound { ref ice }) => {
+            (
+                PrintMessage::Progress { .. } | PrintMessage::Reducing { .. },
+                PrintMessage::IceFound { ref ice },
+            ) => {
                 println!("\r{ice}");
             }
+            (
+                _,
+                PrintMessage::Reducing {
+                    index,
+                    total,
+                    file_name,
+                },
+            ) => {
+                let perc = ((index * 100) as f32 / *total as f32) as u8;
+                print!("\r[{index}/{total} {perc}%] Reducing {file_name: <150}");
+                let _stdout = std::io::stdout().flush();
+            }
             (
                 PrintMessage::IceFound { .. },
                 PrintMessage::Progress {
@@ -0,0 +1,164 @@
+// --time-budget: spend a fixed amount of time on the work that is most likely to find something
+//
+// instead of biggest files first, files are ordered by expected findings per second: findings of
+// earlier runs in the file and in its directory, files that are new since the last run and the
+// time the file took last time. Rustc flag chunks that found a lot before are tried first. Once the
+// budget is spent no new file or flag chunk is started, findings are not minimized and checked on
+// other toolchains and editions anymore and the run finishes as usual.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use once_cell::sync::Lazy;
+
+use crate::ice::{Executable, ICE};
+use crate::library::Args;
+use crate::timings::{flag_set_key, TimingHistory};
+
+// expected value of a finding in the file itself, in the same directory and of a new file
+const FILE_FINDING_WEIGHT: f64 = 10.0;
+const DIR_FINDING_WEIGHT: f64 = 1.0;
+const NEW_FILE_WEIGHT: f64 = 5.0;
+
+// cost of a file if we have no timings at all
+const DEFAULT_COST_MS: f64 = 1000.0;
+
+// starts counting on first use, main() forces it right away
+pub(crate) static DEADLINE: Lazy<Option<Instant>> = Lazy::new(|| {
+    Args::parse()
+        .time_budget
+        .map(|minutes| Instant::now() + Duration::from_secs(minutes * 60))
+});
+
+pub(crate) fn budget_spent() -> bool {
+    DEADLINE.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// "./tests/ui/foo.rs" and "<root>/tests/ui/foo.rs" => "tests/ui/foo.rs"
+fn relative<'a>(file: &'a Path, root_path: &Path) -> &'a Path {
+    let file = file.strip_prefix(root_path).unwrap_or(file);
+    file.strip_prefix("./").unwrap_or(file)
+}
+
+/// expected findings per second
+fn score(file_findings: usize, dir_findings: usize, is_new: bool, cost_ms: f64) -> f64 {
+    let value = 1.0
+        + FILE_FINDING_WEIGHT * file_findings as f64
+        + DIR_FINDING_WEIGHT * dir_findings as f64
+        + if is_new { NEW_FILE_WEIGHT } else { 0.0 };
+    value / cost_ms.max(1.0)
+}
+
+/// most promising files first, `findings` are the findings of the errors.json
+pub(crate) fn prioritize_files(
+    files: &mut [PathBuf],
+    root_path: &Path,
+    findings: &[ICE],
+    history: &TimingHistory,
+) {
+    let mut per_file: HashMap<&Path, usize> = HashMap::new();
+    let mut per_dir: HashMap<&Path, usize> = HashMap::new();
+    for ice in findings {
+        let file = relative(&ice.file, root_path);
+        *per_file.entry(file).or_default() += 1;
+        if let Some(dir) = file.parent() {
+            *per_dir.entry(dir).or_default() += 1;
+        }
+    }
+
+    // the total time a file took in the last run
+    let cost = |file: &Path| {
+        history
+            .files
+            .get(file)
+            .filter(|file_history| file_history.stats.samples > 0)
+            .map(|file_history| {
+                file_history.stats.median_ms as f64 * file_history.stats.samples as f64
+            })
+    };
+    let mut known_costs = files
+        .iter()
+        .filter_map(|file| cost(file))
+        .collect::<Vec<f64>>();
+    known_costs.sort_by(f64::total_cmp);
+    let unknown_cost = known_costs
+        .get(known_costs.len() / 2)
+        .copied()
+        .unwrap_or(DEFAULT_COST_MS);
+
+    let mut scored = files
+        .iter()
+        .map(|file| {
+            let relative_file = relative(file, root_path);
+            let score = score(
+                per_file.get(relative_file).copied().unwrap_or_default(),
+                relative_file
+                    .parent()
+                    .and_then(|dir| per_dir.get(dir))
+                    .copied()
+                    .unwrap_or_default(),
+                !history.files.contains_key(file.as_path()),
+                cost(file).unwrap_or(unknown_cost),
+            );
+            (score, file.clone())
+        })
+        .collect::<Vec<(f64, PathBuf)>>();
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    files
+        .iter_mut()
+        .zip(scored)
+        .for_each(|(file, (_, prioritized))| *file = prioritized);
+}
+
+/// the rustc flag chunks, the ones that found something most often first. Chunks we know nothing
+/// about are assumed to hit now and then
+pub(crate) fn prioritize_flag_chunks<'a>(
+    chunks: &[&'a [&'a str]],
+    history: &TimingHistory,
+) -> Vec<&'a [&'a str]> {
+    let hit_rate = |chunk: &[&str]| {
+        let hit_rate = history
+            .flag_set_hits
+            .get(&flag_set_key(&Executable::Rustc, &chunk.join(" ")))
+            .copied()
+            .unwrap_or_default();
+        // laplace smoothing
+        (hit_rate.hits as f64 + 1.0) / (hit_rate.runs as f64 + 2.0)
+    };
+    let mut chunks = chunks.to_vec();
+    chunks.sort_by(|a, b| hit_rate(b).total_cmp(&hit_rate(a)));
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timings::HitRate;
+
+    #[test]
+    fn findings_per_second() {
+        // a file that crashed before beats a new one, which beats one that never did anything
+        assert!(score(1, 0, false, 1000.0) > score(0, 0, true, 1000.0));
+        assert!(score(0, 0, true, 1000.0) > score(0, 0, false, 1000.0));
+        // cheap files go first
+        assert!(score(0, 0, false, 10.0) > score(0, 0, false, 1000.0));
+    }
+
+    #[test]
+    fn hit_rate_order() {
+        let chunks: &[&[&str]] = &[&["-Za"], &["-Zb"], &["-Zc"]];
+        let mut history = TimingHistory::default();
+        history
+            .flag_set_hits
+            .insert(String::from("Rustc -Za"), HitRate { runs: 100, hits: 0 });
+        history
+            .flag_set_hits
+            .insert(String::from("Rustc -Zb"), HitRate { runs: 10, hits: 5 });
+        assert_eq!(
+            prioritize_flag_chunks(chunks, &history),
+            [&["-Zb"][..], &["-Zc"], &["-Za"]]
+        );
+    }
+}
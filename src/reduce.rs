@@ -0,0 +1,727 @@
+// code reduction
+//
+// we prefer treereduce-rust if it is installed, otherwise we fall back to a simple reducer of our
+// own that deletes or replaces tree-sitter nodes as long as the code is still interesting
+//
+// every mvce is stored in REDUCTION_DIR under a key of (file hash, flags, executable, toolchain)
+// so that --reduce, the reports and the html output never reduce the same finding twice
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use clap::Parser as _;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tempdir::TempDir;
+use tree_sitter::{Node, Parser};
+
+use crate::find_ICE_string;
+use crate::ice::{normalize_ice_msg, Executable, ICEKind, ICE};
+use crate::library::Args;
+use crate::memory_limiter::MEMORY_LIMITER;
+use crate::normalize::normalize_mvce;
+use crate::run_commands::{file_compiles, run_executable};
+
+pub(crate) const REDUCTION_DIR: &str = "icemaker_reduced";
+
+/// give up on reducing a finding after this long, both passes together, --reduce-timeout
+pub(crate) static REDUCTION_TIMEOUT: Lazy<Duration> =
+    Lazy::new(|| Duration::from_secs(Args::parse().reduce_timeout));
+
+static TREEREDUCE_AVAILABLE: Lazy<bool> = Lazy::new(|| {
+    Command::new("treereduce-rust")
+        .arg("--version")
+        .output()
+        .is_ok()
+});
+
+// nodes that we try to remove entirely
+const DELETABLE_KINDS: &[&str] = &[
+    // items
+    "function_item",
+    "function_signature_item",
+    "struct_item",
+    "enum_item",
+    "union_item",
+    "impl_item",
+    "trait_item",
+    "mod_item",
+    "use_declaration",
+    "extern_crate_declaration",
+    "foreign_mod_item",
+    "const_item",
+    "static_item",
+    "type_item",
+    "associated_type",
+    "macro_definition",
+    "field_declaration",
+    "enum_variant",
+    // statements
+    "let_declaration",
+    "expression_statement",
+    // generic params
+    "type_parameters",
+    "lifetime",
+    "constrained_type_parameter",
+    "optional_type_parameter",
+    "const_parameter",
+    "where_clause",
+    "where_predicate",
+    "trait_bounds",
+    // attributes
+    "attribute_item",
+    "inner_attribute_item",
+    "line_comment",
+    "block_comment",
+];
+
+/// a single change to the source: replace start..end with the replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Edit {
+    start: usize,
+    end: usize,
+    replacement: String,
+}
+
+impl Edit {
+    fn apply(&self, source: &str) -> String {
+        format!(
+            "{}{}{}",
+            &source[..self.start],
+            self.replacement,
+            &source[self.end..]
+        )
+    }
+
+    fn removed_bytes(&self) -> usize {
+        (self.end - self.start).saturating_sub(self.replacement.len())
+    }
+}
+
+fn collect_edits(node: Node<'_>, source: &str, edits: &mut Vec<Edit>) {
+    let kind = node.kind();
+    let (start, end) = (node.start_byte(), node.end_byte());
+
+    if DELETABLE_KINDS.contains(&kind) {
+        // also eat a trailing comma of generic params, fields, variants ..
+        let end = match node.next_sibling() {
+            Some(next) if next.kind() == "," => next.end_byte(),
+            _ => end,
+        };
+        edits.push(Edit {
+            start,
+            end,
+            replacement: String::new(),
+        });
+    }
+
+    if kind == "block" {
+        edits.push(Edit {
+            start,
+            end,
+            replacement: String::from("{}"),
+        });
+    }
+
+    // replace an expression by one of its subexpressions: `a + b` => `a`, `foo(x)` => `x`
+    if kind.ends_with("_expression") {
+        let mut cursor = node.walk();
+        node.named_children(&mut cursor)
+            .filter(|child| child.kind().ends_with("_expression") || child.kind() == "block")
+            .for_each(|child| {
+                edits.push(Edit {
+                    start,
+                    end,
+                    replacement: source[child.start_byte()..child.end_byte()].to_string(),
+                })
+            });
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_edits(child, source, edits);
+    }
+}
+
+/// all the ways in which we could make the source smaller, biggest reductions first
+pub(crate) fn tree_sitter_edits(source: &str) -> Vec<Edit> {
+    let mut parser = Parser::new();
+    parser.set_timeout_micros(10_000_000);
+    if parser.set_language(&tree_sitter_rust::language()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+
+    let mut edits = Vec::new();
+    collect_edits(tree.root_node(), source, &mut edits);
+    edits
+}
+
+fn hash(source: &str) -> [u8; 32] {
+    Sha256::digest(source.as_bytes()).into()
+}
+
+/// greedily apply edits as long as the code stays interesting, candidates are tested in parallel
+/// and the biggest interesting reduction wins
+pub(crate) fn reduce_with<E, I>(
+    source: &str,
+    edits: E,
+    is_interesting: I,
+    timeout: Duration,
+) -> String
+where
+    E: Fn(&str) -> Vec<Edit>,
+    I: Fn(&str) -> bool + Sync,
+{
+    let start_time = Instant::now();
+    let batch_size = rayon::current_num_threads().max(1) * 2;
+
+    let mut current = source.to_string();
+    // candidates that we already know to be uninteresting
+    let mut rejected: HashSet<[u8; 32]> = HashSet::new();
+
+    'reduce: loop {
+        let mut candidates = edits(&current)
+            .into_iter()
+            .filter(|edit| edit.removed_bytes() > 0)
+            .collect::<Vec<Edit>>();
+        candidates.sort_by_key(|edit| std::cmp::Reverse(edit.removed_bytes()));
+        candidates.dedup();
+
+        let mut candidates = candidates
+            .iter()
+            .map(|edit| edit.apply(&current))
+            .filter(|candidate| !rejected.contains(&hash(candidate)))
+            .collect::<Vec<String>>();
+        candidates.dedup();
+
+        for batch in candidates.chunks(batch_size) {
+            if start_time.elapsed() > timeout {
+                break 'reduce;
+            }
+            let results = batch
+                .par_iter()
+                .map(|candidate| is_interesting(candidate))
+                .collect::<Vec<bool>>();
+            // batch is sorted, the first interesting candidate removes the most code
+            match results.iter().position(|interesting| *interesting) {
+                Some(idx) => {
+                    current = batch[idx].clone();
+                    continue 'reduce;
+                }
+                None => batch.iter().for_each(|candidate| {
+                    rejected.insert(hash(candidate));
+                }),
+            }
+        }
+        // nothing left that we could remove
+        break;
+    }
+
+    current
+}
+
+// any path to a .rs file, including line and column; paths of the input file differ between the
+// original and the candidates
+static RS_PATH_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[^\s:'`]+\.rs(:\d+(:\d+)?)?").unwrap());
+
+pub(crate) fn comparable_msg(msg: &str) -> String {
+    normalize_ice_msg(&RS_PATH_REGEX.replace_all(msg, " "))
+}
+
+/// the output of a candidate shows the same bug as the finding: same normalized message and, if we
+/// know it, the same panic location inside the compiler
+pub(crate) fn reproduces_ice(ice: &ICE, candidate_file: &Path, output: Output) -> bool {
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stderr),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let Some((msg, _kind, _query_stack)) = find_ICE_string(candidate_file, &ice.executable, output)
+    else {
+        return false;
+    };
+
+    let same_location = ice
+        .panic_location()
+        .is_none_or(|location| text.contains(&location));
+
+    same_location && comparable_msg(&msg) == comparable_msg(&ice.ice_msg)
+}
+
+/// flags of the finding split into compiler and miri flags, without the placeholders for output
+/// files that discover adds
+pub(crate) fn oracle_flags(ice: &ICE) -> (Vec<&str>, Vec<&str>) {
+    split_flags(&ice.executable, &ice.args)
+}
+
+/// like oracle_flags, for the args of a finding that is still being discovered
+pub(crate) fn split_flags<'a>(
+    executable: &Executable,
+    args: &'a [String],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let (miri_flags, mut compiler_flags): (Vec<&str>, Vec<&str>) = args
+        .iter()
+        .map(String::as_str)
+        .filter(|flag| {
+            !(flag.starts_with("-o") && *flag != "-ocodegen" || flag.starts_with("-Zdump-mir-dir"))
+        })
+        .partition(|flag| flag.starts_with("-Zmiri-"));
+
+    // findings from before we kept the miri flags around
+    if *executable == Executable::Miri
+        && !compiler_flags
+            .iter()
+            .any(|flag| flag.starts_with("--edition="))
+    {
+        compiler_flags.push("--edition=2021");
+    }
+    (compiler_flags, miri_flags)
+}
+
+/// run the executable of the ice on the candidate file the same way discover does it
+/// with `must_compile`, the candidate also needs to compile without any of the flags of the ice
+pub(crate) fn candidate_is_interesting(
+    ice: &ICE,
+    candidate_file: &Path,
+    dir: &Path,
+    must_compile: bool,
+) -> bool {
+    // much cheaper than reproducing the ice, so check this first
+    if must_compile
+        && !file_compiles(
+            &candidate_file.to_path_buf(),
+            &Executable::Rustc.path(),
+            &dir.to_path_buf(),
+        )
+    {
+        return false;
+    }
+
+    let (compiler_flags, miri_flags) = oracle_flags(ice);
+    let (output, _cmd, _actual_args) = run_executable(
+        &ice.executable,
+        &ice.executable.path(),
+        candidate_file,
+        false,
+        &compiler_flags,
+        &miri_flags,
+        &dir.to_path_buf(),
+    )
+    .unwrap();
+    reproduces_ice(ice, candidate_file, output)
+}
+
+/// hangs and ooms take way too long to reproduce, everything else can be reduced
+pub(crate) fn is_reducible(ice: &ICE) -> bool {
+    // accept/reject changes are not crashes, the reducer has nothing to look for
+    !matches!(
+        ice.kind,
+        ICEKind::Hang(_) | ICEKind::OOM | ICEKind::AcceptReject(_)
+    )
+        // skip OOMs which treereduce cant really handle
+        && !ice.error_reason.contains("allocating stack failed")
+}
+
+/// only crashes can be "on valid code", for ub or rustfix failures the code is valid anyway
+fn can_be_ice_on_valid(ice: &ICE) -> bool {
+    matches!(ice.kind, ICEKind::Ice(_) | ICEKind::DoubleIce)
+}
+
+/// write the candidate to disk and check if it still reproduces the ice
+fn still_reproduces(
+    candidate: &str,
+    ice: &ICE,
+    tempdir_path: &Path,
+    counter: &AtomicUsize,
+    must_compile: bool,
+) -> bool {
+    // every candidate gets its own dir so that outputfiles and mir dumps do not collide
+    let dir = tempdir_path.join(format!(
+        "candidate_{}",
+        counter.fetch_add(1, Ordering::SeqCst)
+    ));
+    if std::fs::create_dir_all(&dir).is_err() {
+        return false;
+    }
+    // keep the file name, it ends up in the crate name and thus in some ice messages
+    let file = dir.join(
+        ice.file
+            .file_name()
+            .unwrap_or(std::ffi::OsStr::new("file.rs")),
+    );
+    let interesting = std::fs::write(&file, candidate).is_ok()
+        && candidate_is_interesting(ice, &file, &dir, must_compile);
+
+    let _ = std::fs::remove_dir_all(&dir);
+    interesting
+}
+
+/// reduce the file with our own tree-sitter based reducer
+pub(crate) fn reduce_natively(
+    ice: &ICE,
+    file: &Path,
+    tempdir_path: &Path,
+    must_compile: bool,
+    deadline: Instant,
+) -> String {
+    let Ok(source) = std::fs::read_to_string(file) else {
+        return String::new();
+    };
+    let counter = AtomicUsize::new(0);
+    reduce_with(
+        &source,
+        tree_sitter_edits,
+        |candidate| still_reproduces(candidate, ice, tempdir_path, &counter, must_compile),
+        deadline.saturating_duration_since(Instant::now()),
+    )
+}
+
+/// `icemaker --interesting ice.json --candidate file.rs [--candidate-must-compile]`
+/// exits with 0 if the candidate still reproduces the ice, oracle for external reducers
+pub(crate) fn interesting_main(ice_json: &Path, candidate: &Path, must_compile: bool) -> ! {
+    let ice: ICE = std::fs::read_to_string(ice_json)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(|| panic!("failed to read ice from '{}'", ice_json.display()));
+    let dir = std::env::current_dir().expect("could not get CWD!");
+
+    std::process::exit(
+        if candidate_is_interesting(&ice, candidate, &dir, must_compile) {
+            0
+        } else {
+            1
+        },
+    );
+}
+
+/// stdout of the command, None if it did not finish before the deadline. The command runs in its own
+/// process group so that the oracles it started are killed with it
+fn stdout_until(cmd: &mut Command, deadline: Instant) -> Option<String> {
+    let mut child = cmd
+        .process_group(0)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(200)),
+            _ => {
+                let _ = Command::new("kill")
+                    .args(["-s", "KILL", "--", &format!("-{}", child.id())])
+                    .status();
+                let _ = child.wait();
+                let _ = reader.join();
+                return None;
+            }
+        }
+    }
+    reader.join().ok()
+}
+
+fn reduce_with_treereduce(
+    ice: &ICE,
+    file: &PathBuf,
+    tempdir_path: &Path,
+    must_compile: bool,
+    deadline: Instant,
+    oracle_jobs: usize,
+) -> String {
+    let icemaker = std::env::current_exe().expect("could not find the icemaker executable");
+    let ice_json = tempdir_path.join("ice.json");
+    std::fs::write(
+        &ice_json,
+        serde_json::to_string(ice).expect("failed to jsonify ICE"),
+    )
+    .expect("failed to write ice.json for treereduce");
+
+    let mut trd = std::process::Command::new("prlimit");
+    trd.arg(format!("--as={}", 3076_u32 * 1000_u32 * 1000_u32)) // 3 gb of ram
+        .arg("treereduce-rust");
+    // treereduce runs one oracle per cpu by default
+    trd.arg(format!("--jobs={oracle_jobs}"));
+    trd.args([
+        "--quiet",
+        "--passes=10",
+        "--min-reduction=10",
+        "--interesting-exit-code=0",
+        "--on-parse-error",
+        "ignore",
+        "--output", // output to stdout
+        "-",
+    ]);
+
+    trd.arg("--source");
+    trd.arg(file);
+
+    // icemaker --interesting runs the executable inside prlimit
+    trd.arg("--");
+    trd.arg(icemaker);
+    trd.arg("--interesting");
+    trd.arg(&ice_json);
+    trd.arg("--candidate");
+    trd.arg("@@.rs");
+    if must_compile {
+        trd.arg("--candidate-must-compile");
+    }
+    trd.current_dir(tempdir_path);
+
+    // the oracles do not go through our memory limiter, reserve their memory up front
+    let _admission =
+        MEMORY_LIMITER.admit_processes(ice.executable.path().as_ref(), oracle_jobs as u64);
+    stdout_until(&mut trd, deadline).unwrap_or_default()
+}
+
+/// reduce the file of the ice with treereduce-rust, or natively if it is not installed.
+/// treereduce runs `oracle_jobs` candidates at a time, the native reducer uses the current thread
+/// pool
+pub(crate) fn reduce_file(
+    ice: &ICE,
+    file: &PathBuf,
+    tempdir_path: &Path,
+    must_compile: bool,
+    deadline: Instant,
+    oracle_jobs: usize,
+) -> String {
+    if *TREEREDUCE_AVAILABLE {
+        reduce_with_treereduce(ice, file, tempdir_path, must_compile, deadline, oracle_jobs)
+    } else {
+        reduce_natively(ice, file, tempdir_path, must_compile, deadline)
+    }
+}
+
+/// same input, flags, executable and toolchain => same mvce
+pub(crate) fn reduction_key(ice: &ICE, file: &Path) -> Option<String> {
+    let content = std::fs::read(file).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(content));
+    hasher.update(ice.args.join(" "));
+    hasher.update(format!("{:?}", ice.executable));
+    hasher.update(ice.executable.version());
+    let hash = format!("{:x}", hasher.finalize());
+    Some(hash[..16].to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Reduction {
+    // reduced as far as possible, may contain unrelated errors
+    pub(crate) mvce: String,
+    // reduced so that it still compiles without the flags of the ice,
+    // None if the original code did not compile either
+    pub(crate) valid_mvce: Option<String>,
+}
+
+impl Reduction {
+    pub(crate) fn is_ice_on_valid_code(&self) -> bool {
+        self.valid_mvce.is_some()
+    }
+}
+
+// REDUCTION_DIR/<key>.rs          mvce
+// REDUCTION_DIR/<key>.valid.rs    mvce that still compiles without flags
+// REDUCTION_DIR/<key>.not-valid   the original code did not compile, no valid mvce
+fn cache_paths(reduction_dir: &Path, key: &str) -> (PathBuf, PathBuf, PathBuf) {
+    (
+        reduction_dir.join(format!("{key}.rs")),
+        reduction_dir.join(format!("{key}.valid.rs")),
+        reduction_dir.join(format!("{key}.not-valid")),
+    )
+}
+
+/// the reduction of a finding if it was already done, `file` is the original file of the finding
+pub(crate) fn cached_reduction(ice: &ICE, file: &Path, reduction_dir: &Path) -> Option<Reduction> {
+    let key = reduction_key(ice, file)?;
+    let (mvce_path, valid_path, not_valid_path) = cache_paths(reduction_dir, &key);
+    let mvce = std::fs::read_to_string(mvce_path).ok()?;
+    let valid_mvce = std::fs::read_to_string(valid_path).ok();
+    // we did not finish the valid reduction yet
+    if valid_mvce.is_none() && can_be_ice_on_valid(ice) && !not_valid_path.exists() {
+        return None;
+    }
+    Some(Reduction { mvce, valid_mvce })
+}
+
+/// rustfmt the mvce, if rustfmt fails, keep it as it is
+fn format_mvce(mvce: String, tempdir_path: &Path) -> String {
+    // find possible edition flags inside the rustcflags which we will also need to pass to rustfmt?
+    let Ok(mut fmt) = Command::new("rustfmt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .current_dir(tempdir_path)
+        .arg("--edition=2021")
+        .spawn()
+    else {
+        return mvce;
+    };
+
+    let mut stdin = fmt.stdin.take().expect("Failed to open stdin");
+    let mvce_clone = mvce.clone();
+    std::thread::spawn(move || {
+        stdin
+            .write_all(mvce_clone.as_bytes())
+            .expect("Failed to write to stdin");
+    });
+
+    match fmt.wait_with_output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).to_string()
+        }
+        _ => mvce,
+    }
+}
+
+/// normalize and format a fresh reduction, every step has to keep the ice
+fn clean_up(reduced: String, ice: &ICE, tempdir_path: &Path, must_compile: bool) -> String {
+    let counter = AtomicUsize::new(0);
+    let reproduces = |candidate: &str, ice: &ICE| {
+        still_reproduces(candidate, ice, tempdir_path, &counter, must_compile)
+    };
+    let (normalized, ice) = normalize_mvce(reduced, ice, reproduces);
+
+    let formatted = format_mvce(normalized.clone(), tempdir_path);
+    if formatted == normalized || reproduces(&formatted, &ice) {
+        formatted
+    } else {
+        normalized
+    }
+}
+
+fn write_cache(path: &Path, content: &str) {
+    if std::fs::write(path, content).is_err() {
+        eprintln!("failed to write reduction cache {}", path.display());
+    }
+}
+
+/// minimize the code of a finding, once without any constraints and once so that the code still
+/// compiles without the flags of the ice.
+/// a finding is reduced at most once and then taken from the cache
+pub(crate) fn reduce_ice_code(
+    ice: &ICE,
+    global_tempdir_path: &Path,
+    oracle_jobs: usize,
+) -> Option<Reduction> {
+    if !is_reducible(ice) {
+        return None;
+    }
+    // if we run inside a tempdir, we need an absolute path, because the file is not copied into the tempdir
+    let file = ice.file.canonicalize().ok()?;
+    let reduction_dir = PathBuf::from(REDUCTION_DIR);
+    if let Some(reduction) = cached_reduction(ice, &file, &reduction_dir) {
+        return Some(reduction);
+    }
+    let key = reduction_key(ice, &file)?;
+    std::fs::create_dir_all(&reduction_dir).ok()?;
+    let (mvce_path, valid_path, not_valid_path) = cache_paths(&reduction_dir, &key);
+
+    eprintln!("{}", ice.to_printable());
+    let reduction_start_time = Instant::now();
+    let deadline = reduction_start_time + *REDUCTION_TIMEOUT;
+    let tempdir = TempDir::new_in(global_tempdir_path, "icemaker_reducing_tempdir").ok()?;
+
+    let mvce = match std::fs::read_to_string(&mvce_path) {
+        Ok(mvce) => mvce,
+        Err(_) => {
+            let reduced = reduce_file(ice, &file, tempdir.path(), false, deadline, oracle_jobs);
+            if reduced.trim().is_empty() {
+                // the reducer failed, do not cache that
+                return None;
+            }
+            let mvce = clean_up(reduced, ice, tempdir.path(), false);
+            write_cache(&mvce_path, &mvce);
+            mvce
+        }
+    };
+
+    // if the original code does not compile, there is no point in trying to keep it compiling
+    let valid_mvce = if can_be_ice_on_valid(ice)
+        && file_compiles(
+            &file,
+            &Executable::Rustc.path(),
+            &tempdir.path().to_path_buf(),
+        ) {
+        let reduced = reduce_file(ice, &file, tempdir.path(), true, deadline, oracle_jobs);
+        (!reduced.trim().is_empty()).then(|| clean_up(reduced, ice, tempdir.path(), true))
+    } else {
+        None
+    };
+    match &valid_mvce {
+        Some(valid_mvce) => write_cache(&valid_path, valid_mvce),
+        // out of time, maybe there is a valid reduction after all
+        None if Instant::now() >= deadline => {}
+        None => write_cache(&not_valid_path, ""),
+    }
+
+    eprintln!(
+        "reduction took {} seconds",
+        reduction_start_time.elapsed().as_secs()
+    );
+
+    Some(Reduction { mvce, valid_mvce })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one edit per line that removes the line
+    fn line_edits(source: &str) -> Vec<Edit> {
+        let mut start = 0;
+        source
+            .split_inclusive('\n')
+            .map(|line| {
+                let edit = Edit {
+                    start,
+                    end: start + line.len(),
+                    replacement: String::new(),
+                };
+                start += line.len();
+                edit
+            })
+            .collect()
+    }
+
+    #[test]
+    fn greedy_reduction() {
+        let source = "a\nbbbb\nKEEP\ncc\nALSO KEEP\nd\n";
+        let reduced = reduce_with(
+            source,
+            line_edits,
+            |candidate| candidate.lines().any(|line| line == "KEEP") && candidate.contains("ALSO"),
+            Duration::from_secs(60),
+        );
+        assert_eq!(reduced, "KEEP\nALSO KEEP\n");
+    }
+
+    #[test]
+    fn input_paths_do_not_matter() {
+        assert_eq!(
+            comparable_msg("error: internal compiler error: ./tests/ui/foo.rs:3:14: broken MIR in DefId(0:3 ~ foo[1234]::main)"),
+            comparable_msg("error: internal compiler error: /tmp/icemaker_reducing_tempdir.abc/candidate_17/file.rs:1:9: broken MIR in DefId(0:3 ~ file[abcd]::main)")
+        );
+    }
+
+    #[test]
+    fn uninteresting_input_stays() {
+        let source = "a\nb\n";
+        let reduced = reduce_with(source, line_edits, |_| false, Duration::from_secs(60));
+        assert_eq!(reduced, source);
+    }
+}
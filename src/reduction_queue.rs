@@ -0,0 +1,179 @@
+// reduce all findings of an errors.json
+//
+// reductions run concurrently, as many as --reduce-jobs and the available memory allow, and the
+// oracle processes of all reductions together stay within the same memory budget. Findings
+// that share a fingerprint with one that was already reduced are skipped and get its result.
+// Every finished reduction is recorded in REDUCTION_DIR/queue.json, an interrupted --reduce
+// continues where it stopped. Failed reductions are only tried again with a longer --reduce-timeout.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ice::ICE;
+use crate::printing::PrintMessage;
+use crate::reduce::{is_reducible, reduce_ice_code, REDUCTION_DIR, REDUCTION_TIMEOUT};
+use crate::run_commands::parallel_slots;
+use crate::PRINTER;
+
+pub(crate) const QUEUE_STATE_FILE: &str = "queue.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ReductionOutcome {
+    Reduced { ice_on_valid: bool },
+    // the reducer failed or timed out with this --reduce-timeout
+    Failed { timeout_s: u64 },
+}
+
+impl ReductionOutcome {
+    fn is_final(&self, timeout_s: u64) -> bool {
+        match self {
+            ReductionOutcome::Reduced { .. } => true,
+            ReductionOutcome::Failed {
+                timeout_s: failed_with,
+            } => *failed_with >= timeout_s,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct QueueState {
+    // fingerprint => outcome
+    done: BTreeMap<String, ReductionOutcome>,
+}
+
+impl QueueState {
+    fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) {
+        let json = serde_json::to_string_pretty(self).expect("failed to jsonify reduction queue");
+        if std::fs::write(path, json).is_err() {
+            eprintln!("failed to write {}", path.display());
+        }
+    }
+}
+
+/// one finding per fingerprint that was not reduced yet
+fn pending<'a>(ices: &'a [ICE], state: &QueueState, timeout_s: u64) -> Vec<&'a ICE> {
+    let mut seen = HashSet::new();
+    ices.iter()
+        .filter(|ice| is_reducible(ice))
+        .filter(|ice| {
+            let fingerprint = ice.fingerprint();
+            !state
+                .done
+                .get(&fingerprint)
+                .is_some_and(|outcome| outcome.is_final(timeout_s))
+                && seen.insert(fingerprint)
+        })
+        .collect()
+}
+
+/// (reductions at the same time, oracle processes per reduction) so that all oracles together fit
+/// into `slots`
+fn job_budget(jobs: usize, slots: usize) -> (usize, usize) {
+    let slots = slots.max(1);
+    let reductions = jobs.clamp(1, slots);
+    (reductions, (slots / reductions).max(1))
+}
+
+/// reduce the findings with `jobs` reductions at a time and tag them as ice-on-valid if
+/// a valid reduction was found
+pub(crate) fn reduce_findings(ices: Vec<ICE>, global_tempdir_path: &Path, jobs: usize) -> Vec<ICE> {
+    let state_path = PathBuf::from(REDUCTION_DIR).join(QUEUE_STATE_FILE);
+    let state = QueueState::load(&state_path);
+    let timeout_s = REDUCTION_TIMEOUT.as_secs();
+    let queue = pending(&ices, &state, timeout_s);
+
+    let (jobs, oracle_jobs) = job_budget(jobs, parallel_slots());
+    eprintln!(
+        "reducing {} of {} findings with {jobs} jobs of {oracle_jobs} oracles each, the rest were already reduced, share a fingerprint or can not be reduced",
+        queue.len(),
+        ices.len()
+    );
+
+    let total = queue.len();
+    let started = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let state = Mutex::new(state);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .expect("failed to build thread pool for reductions");
+
+    pool.install(|| {
+        queue.par_iter().for_each(|ice| {
+            PRINTER.log(PrintMessage::Reducing {
+                index: started.fetch_add(1, Ordering::SeqCst) + 1,
+                total,
+                file_name: ice.file.display().to_string(),
+            });
+
+            let outcome = match reduce_ice_code(ice, global_tempdir_path, oracle_jobs) {
+                Some(reduction) => ReductionOutcome::Reduced {
+                    ice_on_valid: reduction.is_ice_on_valid_code(),
+                },
+                None => {
+                    failed.fetch_add(1, Ordering::SeqCst);
+                    ReductionOutcome::Failed { timeout_s }
+                }
+            };
+
+            // save after every finding so that we can resume after a ctrl+c
+            let mut state = state.lock().unwrap();
+            state.done.insert(ice.fingerprint(), outcome);
+            state.save(&state_path);
+        })
+    });
+
+    eprintln!(
+        "\nreduced {} findings, {} failed",
+        total - failed.load(Ordering::SeqCst),
+        failed.load(Ordering::SeqCst)
+    );
+
+    let state = state.into_inner().unwrap();
+    ices.into_iter()
+        .map(|mut ice| {
+            if let Some(ReductionOutcome::Reduced { ice_on_valid }) =
+                state.done.get(&ice.fingerprint())
+            {
+                ice.ice_on_valid = *ice_on_valid;
+            }
+            ice
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_with_longer_timeout() {
+        let failed = ReductionOutcome::Failed { timeout_s: 120 };
+        assert!(failed.is_final(120));
+        assert!(!failed.is_final(600));
+        assert!(ReductionOutcome::Reduced {
+            ice_on_valid: false
+        }
+        .is_final(600));
+    }
+
+    #[test]
+    fn oracles_fit_into_slots() {
+        assert_eq!(job_budget(4, 16), (4, 4));
+        assert_eq!(job_budget(32, 16), (16, 1));
+        assert_eq!(job_budget(3, 16), (3, 5));
+        assert_eq!(job_budget(0, 0), (1, 1));
+    }
+}
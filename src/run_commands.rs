@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
 
 use lazy_static::lazy_static;
 use once_cell::sync::Lazy;
@@ -10,8 +12,11 @@ use regex::Regex;
 use clap::Parser;
 use tempdir::TempDir;
 
+use crate::editions::EDITION_FLAGS;
 use crate::ice::Executable;
 use crate::library::{file_has_main, Args};
+use crate::memory_limiter::MEMORY_LIMITER;
+use crate::tempdirs::{confine_args, wait_for_disk_space, RUN_TEMPDIR};
 use crate::{find_ICE_string, flags};
 
 lazy_static! {
@@ -38,6 +43,66 @@ static SYSROOT_PATH: Lazy<String> = Lazy::new(|| {
     )
 });
 
+// toolchains that `cargo miri setup` ran for, and whether it worked
+static MIRI_SETUP: Lazy<Mutex<HashMap<String, bool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn default_toolchain() -> &'static str {
+    if *LOCAL_DEBUG_ASSERTIONS {
+        "local-debug-assertions"
+    } else {
+        "master"
+    }
+}
+
+/// the rustup toolchain a binary belongs to
+/// ~/.rustup/toolchains/beta-x86_64-unknown-linux-gnu/bin/rustdoc => beta-x86_64-unknown-linux-gnu
+fn toolchain_of(executable: &str) -> Option<&str> {
+    let bin = Path::new(executable).parent()?;
+    let toolchain = bin.parent()?;
+    if bin.file_name()? != "bin" || toolchain.parent()?.file_name()? != "toolchains" {
+        return None;
+    }
+    toolchain.file_name()?.to_str()
+}
+
+/// env for running a tool of some toolchain: its own sysroot, so that stable clippy is not run
+/// against the std of master, and RUSTC_BOOTSTRAP so that stable and beta accept our -Z flags
+fn toolchain_env(executable: &str) -> Vec<(&'static str, String)> {
+    match toolchain_of(executable) {
+        Some(toolchain) if toolchain != default_toolchain() => {
+            let sysroot = Path::new(executable).parent().and_then(Path::parent);
+            vec![
+                ("SYSROOT", sysroot.unwrap().display().to_string()),
+                ("RUSTC_BOOTSTRAP", String::from("1")),
+            ]
+        }
+        _ => vec![("SYSROOT", SYSROOT_PATH.clone())],
+    }
+}
+
+/// +toolchain for cargo based runs
+fn cargo_toolchain(executable: &str) -> String {
+    format!(
+        "+{}",
+        toolchain_of(executable).unwrap_or(default_toolchain())
+    )
+}
+
+/// build the sysroot of miri for the toolchain, once. Without it cargo miri tries to build std
+/// itself, false if the setup failed
+pub(crate) fn miri_setup(toolchain: &str) -> bool {
+    let mut setup = MIRI_SETUP.lock().unwrap();
+    *setup.entry(toolchain.to_string()).or_insert_with(|| {
+        println!("Running cargo miri setup for {toolchain}");
+        Command::new("cargo")
+            .arg(format!("+{toolchain}"))
+            .arg("miri")
+            .arg("setup")
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
 pub(crate) const PROCESS_TIMEOUT_S: u8 = 30;
 pub(crate) const PROCESS_TIMEOUT_MIRI_S: u8 = 20;
 
@@ -83,6 +148,64 @@ fn get_cmd_string(cmd: &std::process::Command) -> String {
     format!("\"{envs}\" {command}").replace('"', "")
 }
 
+/// run a file through an executable, this is how `ICE::discover` checks a file and what the
+/// reducers use to check if a candidate is still interesting
+pub(crate) fn run_executable(
+    executable: &Executable,
+    exec_path: &str,
+    file: &Path,
+    incremental: bool,
+    compiler_flags: &[&str],
+    miri_flags: &[&str],
+    global_tempdir_path: &PathBuf,
+) -> CommandOutput {
+    match executable {
+        Executable::Clippy => run_clippy(exec_path, file, global_tempdir_path),
+        Executable::ClippyFix => run_clippy_fix(exec_path, file, global_tempdir_path),
+        Executable::RustFix => run_rustfix(exec_path, file, global_tempdir_path),
+        Executable::Rustc => run_rustc(
+            exec_path,
+            file,
+            incremental,
+            compiler_flags,
+            global_tempdir_path,
+        ),
+        Executable::Rustdoc => run_rustdoc(exec_path, file, global_tempdir_path),
+        Executable::RustAnalyzer => run_compare_ra_to_rustc(exec_path, file, global_tempdir_path),
+        Executable::Rustfmt => run_rustfmt(exec_path, file, global_tempdir_path),
+        Executable::Miri => run_miri(
+            exec_path,
+            file,
+            miri_flags,
+            compiler_flags,
+            global_tempdir_path,
+        ),
+
+        Executable::Cranelift => {
+            let mut compiler_flags = compiler_flags.to_vec();
+            compiler_flags.push("-Zcodegen-backend=cranelift");
+            run_rustc(
+                exec_path,
+                file,
+                incremental,
+                &compiler_flags,
+                global_tempdir_path,
+            )
+        }
+        Executable::Kani => run_kani(
+            exec_path,
+            file,
+            miri_flags, // hack
+            compiler_flags,
+            global_tempdir_path,
+        ),
+        Executable::RustcCodegenGCC => {
+            rustc_codegen_gcc_local(exec_path, file, false, compiler_flags, global_tempdir_path)
+        }
+        Executable::Marker => run_marker(file, compiler_flags, global_tempdir_path),
+    }
+}
+
 pub(crate) fn run_rustc(
     executable: &str,
     file: &Path,
@@ -201,7 +324,7 @@ pub(crate) fn run_rustc_incremental(
         }
         command
             .arg(file)
-            .env("SYSROOT", &*SYSROOT_PATH)
+            .envs(toolchain_env(executable))
             // avoid error: the generated executable for the input file  .. onflicts with the existing directory..
             .arg(format!("-o{}/{}", tempdir_path.display(), i))
             .arg(format!("-Cincremental={}", tempdir_path.display()))
@@ -265,7 +388,7 @@ pub(crate) fn run_rustc_lazy_type_alias(
         }
         command
             .arg(file)
-            .env("SYSROOT", &*SYSROOT_PATH)
+            .envs(toolchain_env(executable))
             // avoid error: the generated executable for the input file  .. onflicts with the existing directory..
             .arg(format!("-o{}/{}", tempdir_path.display(), i))
             .arg("--edition=2021")
@@ -480,7 +603,7 @@ pub(crate) fn run_rustc_incremental_with_two_files(
         }
         command
             .arg(input)
-            .env("SYSROOT", &*SYSROOT_PATH)
+            .envs(toolchain_env(executable))
             // avoid error: the generated executable for the input file  .. onflicts with the existing directory..
             .arg(format!("-o{}/{}", tempdir_path.display(), i))
             .arg(format!("-Cincremental={}", tempdir_path.display()))
@@ -532,7 +655,7 @@ pub(crate) fn run_clippy(
     }
 
     cmd.env("RUSTFLAGS", "-Z force-unstable-if-unmarked")
-        .env("SYSROOT", &*SYSROOT_PATH)
+        .envs(toolchain_env(executable))
         .env("CARGO_TERM_COLOR", "never")
         .arg(file)
         .args(flags::CLIPPYLINTS)
@@ -557,7 +680,7 @@ pub(crate) fn run_clippy(
 }
 
 pub(crate) fn run_clippy_fix(
-    _executable: &str,
+    executable: &str,
     file: &Path,
     global_tempdir_path: &PathBuf,
 ) -> CommandOutput {
@@ -600,7 +723,7 @@ pub(crate) fn run_clippy_fix(
 
     // create a new cargo project inside the tmpdir
     if !std::process::Command::new("cargo")
-        .env("SYSROOT", &*SYSROOT_PATH)
+        .envs(toolchain_env(executable))
         .env("CARGO_TERM_COLOR", "never")
         .arg("new")
         .args(["--vcs", "none"])
@@ -644,22 +767,18 @@ pub(crate) fn run_clippy_fix(
 
     let mut cmd = Command::new("cargo");
 
-    cmd.arg(if *LOCAL_DEBUG_ASSERTIONS {
-        "+local-debug-assertions"
-    } else {
-        "+master"
-    })
-    .arg("clippy")
-    .env("CARGO_TERM_COLOR", "never")
-    .env("RUSTFLAGS", "-Z force-unstable-if-unmarked")
-    .env("SYSROOT", &*SYSROOT_PATH)
-    .current_dir(crate_path)
-    .arg("--fix")
-    .arg("--allow-no-vcs")
-    .arg("--")
-    .args(flags::CLIPPYLINTS)
-    .args(flags::RUSTC_ALLOW_BY_DEFAULT_LINTS)
-    .args(["--cap-lints", "warn"]);
+    cmd.arg(cargo_toolchain(executable))
+        .arg("clippy")
+        .env("CARGO_TERM_COLOR", "never")
+        .env("RUSTFLAGS", "-Z force-unstable-if-unmarked")
+        .envs(toolchain_env(executable))
+        .current_dir(crate_path)
+        .arg("--fix")
+        .arg("--allow-no-vcs")
+        .arg("--")
+        .args(flags::CLIPPYLINTS)
+        .args(flags::RUSTC_ALLOW_BY_DEFAULT_LINTS)
+        .args(["--cap-lints", "warn"]);
     //dbg!(&cmd);
 
     let output = prlimit_run_command(&mut cmd).unwrap();
@@ -717,7 +836,7 @@ pub(crate) fn run_clippy_fix(
 }
 
 pub(crate) fn run_rustfix(
-    _executable: &str,
+    executable: &str,
     file: &Path,
     global_tempdir_path: &PathBuf,
 ) -> CommandOutput {
@@ -733,7 +852,7 @@ pub(crate) fn run_rustfix(
     let tempdir_path = tempdir.path();
     // create a new cargo project inside the tmpdir
     if !std::process::Command::new("cargo")
-        .env("SYSROOT", &*SYSROOT_PATH)
+        .envs(toolchain_env(executable))
         .env("CARGO_TERM_COLOR", "never")
         .arg("new")
         .args(["--vcs", "none"])
@@ -773,18 +892,14 @@ pub(crate) fn run_rustfix(
 
     let mut cmd = Command::new("cargo");
 
-    cmd.arg(if *LOCAL_DEBUG_ASSERTIONS {
-        "+local-debug-assertions"
-    } else {
-        "+master"
-    })
-    .env("RUSTFLAGS", "-Z force-unstable-if-unmarked -Aunused")
-    .env("SYSROOT", &*SYSROOT_PATH)
-    .env("CARGO_TERM_COLOR", "never")
-    .current_dir(&crate_path)
-    .arg("fix")
-    .arg("--allow-no-vcs")
-    .arg("--broken-code");
+    cmd.arg(cargo_toolchain(executable))
+        .env("RUSTFLAGS", "-Z force-unstable-if-unmarked -Aunused")
+        .envs(toolchain_env(executable))
+        .env("CARGO_TERM_COLOR", "never")
+        .current_dir(&crate_path)
+        .arg("fix")
+        .arg("--allow-no-vcs")
+        .arg("--broken-code");
     // cargo fix doesnt need this
     // .args(["--", "--cap-lints", "warn"]);
 
@@ -861,7 +976,7 @@ pub(crate) fn run_clippy_fix_with_args(
     let tempdir_path = tempdir.path();
     // create a new cargo project inside the tmpdir
     if !std::process::Command::new("cargo")
-        .env("SYSROOT", &*SYSROOT_PATH)
+        .envs(toolchain_env(executable))
         .env("CARGO_TERM_COLOR", "never")
         .arg("new")
         .args(["--vcs", "none"])
@@ -906,25 +1021,21 @@ pub(crate) fn run_clippy_fix_with_args(
 
     let mut cmd = Command::new("cargo");
 
-    cmd.arg(if *LOCAL_DEBUG_ASSERTIONS {
-        "+local-debug-assertions"
-    } else {
-        "+master"
-    })
-    .arg("clippy")
-    .env("RUSTFLAGS", "-Z force-unstable-if-unmarked")
-    .env("SYSROOT", &*SYSROOT_PATH)
-    .env("CARGO_TERM_COLOR", "never")
-    .current_dir(crate_path)
-    .arg("--fix")
-    .arg("--allow-no-vcs")
-    .arg("--")
-    .arg("-Aclippy::all")
-    // need to silence all default rustc lints first so we can properly bisect them
-    // also add
-    .arg("-Awarnings")
-    .args(args.iter().flat_map(|a| a.split_whitespace()))
-    .args(["--cap-lints", "warn"]);
+    cmd.arg(cargo_toolchain(executable))
+        .arg("clippy")
+        .env("RUSTFLAGS", "-Z force-unstable-if-unmarked")
+        .envs(toolchain_env(executable))
+        .env("CARGO_TERM_COLOR", "never")
+        .current_dir(crate_path)
+        .arg("--fix")
+        .arg("--allow-no-vcs")
+        .arg("--")
+        .arg("-Aclippy::all")
+        // need to silence all default rustc lints first so we can properly bisect them
+        // also add
+        .arg("-Awarnings")
+        .args(args.iter().flat_map(|a| a.split_whitespace()))
+        .args(["--cap-lints", "warn"]);
 
     //dbg!(&cmd);
 
@@ -948,7 +1059,7 @@ pub(crate) fn run_rustdoc(
 ) -> CommandOutput {
     let mut cmd = Command::new(executable);
     cmd.env("RUSTFLAGS", "-Z force-unstable-if-unmarked")
-        .env("SYSROOT", &*SYSROOT_PATH)
+        .envs(toolchain_env(executable))
         .env("CARGO_TERM_COLOR", "never")
         .arg(file)
         .arg("-Znormalize-docs")
@@ -988,7 +1099,6 @@ pub(crate) fn run_rust_analyzer(
     let mut cmd = Command::new("prlimit")
         .args(["--noheadings", "--as=3076000000", "--cpu=30"])
         .arg(executable)
-        // THIS cmd NEEDS TO BE IN SYNC WITH fn reduce_ice_code_to_string() !!
         .arg("highlight")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -1021,7 +1131,7 @@ pub(crate) fn run_rustfmt(
     _global_tempdir_path: &Path,
 ) -> CommandOutput {
     let mut cmd = Command::new(executable);
-    cmd.env("SYSROOT", &*SYSROOT_PATH)
+    cmd.envs(toolchain_env(executable))
         .arg(file)
         .arg("--check")
         .args(["--color", "never"])
@@ -1147,10 +1257,11 @@ pub(crate) fn run_miri(
             .current_dir(crate_path)
             .env("MIRIFLAGS", miri_flags.join(" "));
     } else { */
-    cmd.arg(if *LOCAL_DEBUG_ASSERTIONS {
-        "+local-debug-assertions"
+    // the reproduction matrix passes the cargo-miri of the toolchain it wants to run
+    cmd.arg(if executable.ends_with("cargo-miri") {
+        cargo_toolchain(executable)
     } else {
-        "+master"
+        format!("+{}", default_toolchain())
     })
     .arg("miri")
     .arg(if has_test && !has_main { "test" } else { "run" })
@@ -1579,16 +1690,53 @@ pub(crate) fn run_marker(
     )
 }
 
+// every run is prlimited to 3 GB of address space, see prlimit_run_command
+pub(crate) const MEMORY_PER_RUN_KB: u64 = 3076 * 1000;
+
+pub(crate) fn mem_available_kb() -> Option<u64> {
+    std::fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|meminfo| {
+            meminfo
+                .lines()
+                .find(|line| line.starts_with("MemAvailable:"))
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|kb| kb.parse::<u64>().ok())
+        })
+}
+
+/// how many rustcs we can run at the same time without running out of memory
+pub(crate) fn parallel_slots() -> usize {
+    let cpus = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    match mem_available_kb() {
+        Some(kb) => ((kb / MEMORY_PER_RUN_KB) as usize).clamp(1, cpus),
+        None => 1,
+    }
+}
+
 pub(crate) fn prlimit_run_command(
     new_command: &mut std::process::Command,
 ) -> std::result::Result<Output, std::io::Error> {
     // THIS SHOULD ONLY DO THE ABSOLUTE MINIMUM
     // do not add any additional flags here to the cmd
 
+    // pause while the disk is almost full, then wait until the process fits into the
+    // --memory-budget
+    wait_for_disk_space();
+    let admission = MEMORY_LIMITER.admit(new_command.get_program());
+
     // deconstruct our previous Command and wrap it by a "prlimit run ..."
     if cfg!(feature = "ci") {
         // return as is
-        new_command.output()
+        admission.wait_with_output(
+            new_command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?,
+        )
     } else {
         let program = new_command.get_program();
         let args = new_command.get_args();
@@ -1624,12 +1772,40 @@ pub(crate) fn prlimit_run_command(
         cmd.arg(format!("--cpu={runtime_limit}"));
 
         cmd.arg(program);
-        cmd.args(args);
+        // nothing may write outside of the run tempdir
+        // removed once the process is done
+        let mut confined_tempdir = None;
+        match RUN_TEMPDIR.get() {
+            Some(run_tempdir) => {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                let current_dir = current_dir.unwrap_or(&cwd);
+                // a tempdir that the process runs in is its own already
+                let confined_dir =
+                    if current_dir.starts_with(run_tempdir) && current_dir != run_tempdir {
+                        current_dir.to_owned()
+                    } else {
+                        let tempdir = TempDir::new_in(run_tempdir, "icemaker_confined_tempdir")?;
+                        confined_tempdir.insert(tempdir).path().to_owned()
+                    };
+                let args = args.map(|arg| arg.to_owned()).collect::<Vec<OsString>>();
+                cmd.args(confine_args(&args, current_dir, run_tempdir, &confined_dir));
+                cmd.env("TMPDIR", run_tempdir);
+            }
+            None => {
+                cmd.args(args);
+            }
+        }
         if let Some(dir) = current_dir {
             cmd.current_dir(dir);
         }
         cmd.envs(envs);
-        cmd.output()
+        // what .output() would do
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let output = admission.wait_with_output(cmd.spawn()?);
+        drop(confined_tempdir);
+        output
     }
 }
 
@@ -1646,33 +1822,30 @@ pub(crate) fn file_compiles(
     let tempdir = TempDir::new_in(global_tempdir_path, "rustc_testrunner_tmpdir").unwrap();
     let tempdir_path = tempdir.path();
 
-    ["2015", "2018", "2021", "2024"]
-        .iter()
-        .map(|year| format!("--edition={year}"))
-        .any(|edition_flag| {
-            let mut cmd = Command::new(executable);
-            if !has_main {
-                cmd.arg("--crate-type=lib");
-            } else {
-                cmd.arg("--crate-type=bin");
-            }
-            cmd.arg(&file)
-                .arg("-Zno-codegen")
-                .arg("-Zforce-unstable-if-unmarked")
-                .arg(edition_flag)
-                .args(["--cap-lints", "warn"])
-                .env("CARGO_TERM_COLOR", "never")
-                .current_dir(tempdir_path)
-                .env("CARGO_TERM_COLOR", "never")
-                .env("SYSROOT", &*SYSROOT_PATH);
-
-            matches!(
-                prlimit_run_command(&mut cmd)
-                    .ok()
-                    .map(|x| x.status.success()),
-                Some(true)
-            )
-        })
+    EDITION_FLAGS.iter().any(|edition_flag| {
+        let mut cmd = Command::new(executable);
+        if !has_main {
+            cmd.arg("--crate-type=lib");
+        } else {
+            cmd.arg("--crate-type=bin");
+        }
+        cmd.arg(&file)
+            .arg("-Zno-codegen")
+            .arg("-Zforce-unstable-if-unmarked")
+            .arg(edition_flag)
+            .args(["--cap-lints", "warn"])
+            .env("CARGO_TERM_COLOR", "never")
+            .current_dir(tempdir_path)
+            .env("CARGO_TERM_COLOR", "never")
+            .envs(toolchain_env(executable));
+
+        matches!(
+            prlimit_run_command(&mut cmd)
+                .ok()
+                .map(|x| x.status.success()),
+            Some(true)
+        )
+    })
 }
 
 pub(crate) fn file_compiles_from_string(
@@ -1694,33 +1867,30 @@ pub(crate) fn file_compiles_from_string(
 
     // to path
     let file = file_path;
-    ["2015", "2018", "2021", "2024"]
-        .iter()
-        .map(|year| format!("--edition={year}"))
-        .any(|edition_flag| {
-            let mut cmd = Command::new(executable);
-            if !has_main {
-                cmd.arg("--crate-type=lib");
-            } else {
-                cmd.arg("--crate-type=bin");
-            }
-            cmd.arg(&file)
-                .arg("-Zno-codegen")
-                .arg("-Zforce-unstable-if-unmarked")
-                .arg(edition_flag)
-                .args(["--cap-lints", "warn"])
-                .env("CARGO_TERM_COLOR", "never")
-                .current_dir(tempdir_path)
-                .env("CARGO_TERM_COLOR", "never")
-                .env("SYSROOT", &*SYSROOT_PATH);
-
-            matches!(
-                prlimit_run_command(&mut cmd)
-                    .ok()
-                    .map(|x| x.status.success()),
-                Some(true)
-            )
-        })
+    EDITION_FLAGS.iter().any(|edition_flag| {
+        let mut cmd = Command::new(executable);
+        if !has_main {
+            cmd.arg("--crate-type=lib");
+        } else {
+            cmd.arg("--crate-type=bin");
+        }
+        cmd.arg(&file)
+            .arg("-Zno-codegen")
+            .arg("-Zforce-unstable-if-unmarked")
+            .arg(edition_flag)
+            .args(["--cap-lints", "warn"])
+            .env("CARGO_TERM_COLOR", "never")
+            .current_dir(tempdir_path)
+            .env("CARGO_TERM_COLOR", "never")
+            .envs(toolchain_env(executable));
+
+        matches!(
+            prlimit_run_command(&mut cmd)
+                .ok()
+                .map(|x| x.status.success()),
+            Some(true)
+        )
+    })
 }
 
 pub(crate) fn incremental_stress_test(
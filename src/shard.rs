@@ -0,0 +1,148 @@
+// split the corpus between several machines and merge their results again
+//
+// a file belongs to a shard by the hash of its content, so every machine with the same checkout
+// agrees on the partition without talking to the others, no matter in which order it walks the
+// directories. The errors.json files of the shards are merged with --merge.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use crate::ice::{read_errors_json, ICE};
+
+/// --shard 2/4 checks the second of four parts of the corpus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Shard {
+    // 1-based
+    index: u64,
+    count: u64,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected i/n, got '{s}'"))?;
+        let index = index
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("bad shard index '{index}': {e}"))?;
+        let count = count
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("bad shard count '{count}': {e}"))?;
+        if index == 0 || index > count {
+            return Err(format!(
+                "shard index must be within 1..={count}, got {index}"
+            ));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl Shard {
+    fn contains_content(&self, content: &[u8]) -> bool {
+        let hash = Sha256::digest(content);
+        let bucket = u64::from_le_bytes(hash[..8].try_into().unwrap()) % self.count;
+        bucket == self.index - 1
+    }
+
+    /// is the file part of this shard? unreadable files go to the first shard
+    pub(crate) fn contains(&self, file: &Path) -> bool {
+        match std::fs::read(file) {
+            Ok(content) => self.contains_content(&content),
+            Err(_) => self.index == 1,
+        }
+    }
+}
+
+/// the findings of all errors.json files, one per fingerprint, the first file wins. The files of
+/// the other findings with that fingerprint are kept in its duplicate_files
+pub(crate) fn merge_errors_jsons(errors_jsons: &[PathBuf]) -> Vec<ICE> {
+    let mut merged: Vec<ICE> = Vec::new();
+    let mut by_fingerprint: HashMap<String, usize> = HashMap::new();
+    for errors_json in errors_jsons {
+        let findings = read_errors_json(errors_json);
+        eprintln!(
+            "merging {} findings from {}",
+            findings.len(),
+            errors_json.display()
+        );
+        for ice in findings {
+            match by_fingerprint.get(&ice.fingerprint()) {
+                Some(&index) => {
+                    let representative = &mut merged[index];
+                    let files = std::iter::once(ice.file).chain(ice.duplicate_files);
+                    for file in files {
+                        if file != representative.file
+                            && !representative.duplicate_files.contains(&file)
+                        {
+                            representative.duplicate_files.push(file);
+                        }
+                    }
+                }
+                None => {
+                    by_fingerprint.insert(ice.fingerprint(), merged.len());
+                    merged.push(ice);
+                }
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("2/4".parse::<Shard>(), Ok(Shard { index: 2, count: 4 }));
+        assert!("0/4".parse::<Shard>().is_err());
+        assert!("5/4".parse::<Shard>().is_err());
+        assert!("2".parse::<Shard>().is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let ice = |file: &str, location: &str| ICE {
+            file: PathBuf::from(file),
+            ice_msg: format!("thread 'rustc' panicked at compiler/rustc_middle/src/{location}:1:1"),
+            ..Default::default()
+        };
+        let tempdir = tempdir::TempDir::new("icemaker_merge").unwrap();
+        let write = |name: &str, ices: &[ICE]| {
+            let path = tempdir.path().join(name);
+            std::fs::write(&path, serde_json::to_string(ices).unwrap()).unwrap();
+            path
+        };
+        let shards = [
+            write("a.json", &[ice("a.rs", "ty.rs"), ice("b.rs", "mir.rs")]),
+            write("b.json", &[ice("c.rs", "ty.rs"), ice("b.rs", "mir.rs")]),
+        ];
+        let merged = merge_errors_jsons(&shards);
+        // the same crash in another file is a duplicate
+        assert_eq!(merged, [ice("a.rs", "ty.rs"), ice("b.rs", "mir.rs")]);
+        assert_eq!(merged[0].duplicate_files, [PathBuf::from("c.rs")]);
+        assert!(merged[1].duplicate_files.is_empty());
+    }
+
+    #[test]
+    fn every_file_in_exactly_one_shard() {
+        let shards = (1..=3)
+            .map(|index| Shard { index, count: 3 })
+            .collect::<Vec<_>>();
+        for i in 0..100 {
+            let content = format!("fn f{i}() {{}}");
+            let owners = shards
+                .iter()
+                .filter(|shard| shard.contains_content(content.as_bytes()))
+                .count();
+            assert_eq!(owners, 1);
+        }
+    }
+}
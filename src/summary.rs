@@ -0,0 +1,145 @@
+// machine readable summary of a run, written to summary.json in the cwd so that it can be
+// plotted over time by whatever runs icemaker every night
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::flags::{MIRIFLAGS, MIRI_RUSTFLAGS, RUSTC_FLAGS};
+use crate::ice::{Executable, ICE};
+
+pub(crate) const SUMMARY_FILE: &str = "summary.json";
+
+/// what check_dir found in a single project
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct ProjectSummary {
+    pub(crate) project: PathBuf,
+    pub(crate) files_checked: usize,
+    pub(crate) findings: usize,
+    // findings that were not in the errors.json before
+    pub(crate) new: usize,
+    // findings of the previous errors.json that are gone
+    pub(crate) fixed: usize,
+    pub(crate) by_kind: BTreeMap<String, usize>,
+    pub(crate) by_executable: BTreeMap<String, usize>,
+}
+
+impl ProjectSummary {
+    pub(crate) fn new(
+        project: &Path,
+        files_checked: usize,
+        errors: &[ICE],
+        errors_before: &[ICE],
+    ) -> Self {
+        let mut by_kind = BTreeMap::new();
+        let mut by_executable = BTreeMap::new();
+        errors.iter().for_each(|ice| {
+            *by_kind.entry(ice.kind.name().to_string()).or_default() += 1;
+            *by_executable
+                .entry(format!("{:?}", ice.executable))
+                .or_default() += 1;
+        });
+
+        ProjectSummary {
+            project: project.to_path_buf(),
+            files_checked,
+            findings: errors.len(),
+            new: errors
+                .iter()
+                .filter(|ice| !errors_before.contains(ice))
+                .count(),
+            fixed: errors_before
+                .iter()
+                .filter(|ice| !errors.contains(ice))
+                .count(),
+            by_kind,
+            by_executable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct RunSummary {
+    pub(crate) date: String,
+    pub(crate) projects: Vec<ProjectSummary>,
+    pub(crate) files_checked: usize,
+    // number of (file, executable, flags) combinations that were run
+    pub(crate) work_units: usize,
+    pub(crate) findings: usize,
+    pub(crate) new: usize,
+    pub(crate) fixed: usize,
+    pub(crate) by_kind: BTreeMap<String, usize>,
+    pub(crate) by_executable: BTreeMap<String, usize>,
+    pub(crate) seconds_elapsed: f64,
+    pub(crate) files_per_second: f64,
+    pub(crate) work_units_per_second: f64,
+    // executable => seconds spent
+    pub(crate) tool_seconds: BTreeMap<String, u64>,
+    // executable => `--version` output
+    pub(crate) toolchain_versions: BTreeMap<String, String>,
+    // changes whenever the set of flags we check with changes
+    pub(crate) flags_matrix_id: String,
+}
+
+/// short hash of all flag combinations we run with
+pub(crate) fn flags_matrix_id() -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{:?}", *RUSTC_FLAGS));
+    hasher.update(format!("{MIRIFLAGS:?}"));
+    hasher.update(format!("{MIRI_RUSTFLAGS:?}"));
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..12].to_string()
+}
+
+impl RunSummary {
+    pub(crate) fn new(
+        projects: Vec<ProjectSummary>,
+        work_units: usize,
+        seconds_elapsed: f64,
+        tool_seconds: BTreeMap<String, u64>,
+        executables: &[Executable],
+    ) -> Self {
+        let mut summary = RunSummary {
+            date: chrono::offset::Local::now().to_rfc3339(),
+            work_units,
+            seconds_elapsed,
+            tool_seconds,
+            toolchain_versions: executables
+                .iter()
+                .map(|exe| (format!("{exe:?}"), exe.version()))
+                .collect(),
+            flags_matrix_id: flags_matrix_id(),
+            ..Default::default()
+        };
+
+        for project in &projects {
+            summary.files_checked += project.files_checked;
+            summary.findings += project.findings;
+            summary.new += project.new;
+            summary.fixed += project.fixed;
+            for (kind, count) in &project.by_kind {
+                *summary.by_kind.entry(kind.clone()).or_default() += count;
+            }
+            for (exe, count) in &project.by_executable {
+                *summary.by_executable.entry(exe.clone()).or_default() += count;
+            }
+        }
+        summary.projects = projects;
+
+        if seconds_elapsed > 0.0 {
+            summary.files_per_second = summary.files_checked as f64 / seconds_elapsed;
+            summary.work_units_per_second = work_units as f64 / seconds_elapsed;
+        }
+
+        summary
+    }
+
+    pub(crate) fn to_disk(&self, path: &Path) {
+        let json = serde_json::to_string_pretty(self).expect("failed to jsonify summary");
+        std::fs::write(path, json)
+            .unwrap_or_else(|_| panic!("failed to write summary to {}", path.display()));
+        eprintln!("summary was written to {}", path.display());
+    }
+}
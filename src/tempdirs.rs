@@ -0,0 +1,281 @@
+// keep the files of a run inside its global tempdir and the disk from filling up
+//
+// the global tempdir is only removed on ctrl-c or a normal exit, a run that panics leaves it
+// behind. Every run writes its pid into its tempdir and removes the tempdirs of runs that are not
+// alive anymore when it starts. Tools get the run tempdir as TMPDIR (rustc -Csave-temps creates
+// rustc* dirs in there and does not remove them) and flags that would write somewhere else are
+// pointed into a tempdir of the process inside the run tempdir. While the disk is almost full no new
+// process is started.
+
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::Parser;
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::library::Args;
+
+const PID_FILE: &str = "icemaker.pid";
+const GLOBAL_TEMPDIR_PREFIX: &str = "icemaker_global_tempdir";
+// tempdirs without a pid file are from older versions, we can only go by their age
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// flags that take a path that rustc writes to, as flag=path
+const OUTPUT_PATH_FLAGS: &[&str] = &[
+    "--out-dir",
+    "-Zdump-mir-dir",
+    "-Ztemps-dir",
+    "-Cincremental",
+    "-Cprofile-generate",
+    "-Zself-profile",
+];
+
+// the global tempdir of this run, set once it is created
+pub(crate) static RUN_TEMPDIR: OnceCell<PathBuf> = OnceCell::new();
+
+static MIN_FREE_DISK_KB: Lazy<u64> = Lazy::new(|| Args::parse().min_free_disk * 1000 * 1000);
+
+static LAST_DISK_CHECK: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+/// remember the global tempdir of this run and remove the ones of dead runs next to it
+pub(crate) fn init_run_tempdir(global_tempdir_path: &Path) {
+    if let Err(e) = std::fs::write(
+        global_tempdir_path.join(PID_FILE),
+        std::process::id().to_string(),
+    ) {
+        eprintln!("WARNING: failed to write pid file: {e}");
+    }
+    if let Some(parent) = global_tempdir_path.parent() {
+        remove_stale_tempdirs(parent, global_tempdir_path);
+    }
+    // rustc*-dirs that -Csave-temps left in /tmp/ before TMPDIR pointed into the run tempdir
+    remove_stale_rustc_tempdirs(&std::env::temp_dir());
+    let _ = RUN_TEMPDIR.set(global_tempdir_path.to_owned());
+}
+
+fn is_alive(pid: &str) -> bool {
+    std::fs::read(format!("/proc/{pid}/cmdline"))
+        .is_ok_and(|cmdline| String::from_utf8_lossy(&cmdline).contains("icemaker"))
+}
+
+fn is_old(path: &Path) -> bool {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_AFTER)
+}
+
+/// global tempdirs of runs whose process is gone
+fn remove_stale_tempdirs(dir: &Path, own_tempdir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path == own_tempdir || !path.is_dir() {
+            continue;
+        }
+        let stale = match std::fs::read_to_string(path.join(PID_FILE)) {
+            Ok(pid) => !is_alive(pid.trim()),
+            Err(_) => {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(GLOBAL_TEMPDIR_PREFIX))
+                    && is_old(&path)
+            }
+        };
+        if stale {
+            eprintln!("removing stale tempdir '{}'", path.display());
+            if std::fs::remove_dir_all(&path).is_err() {
+                eprintln!(
+                    "WARNING: failed to remove stale tempdir '{}'",
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+/// "rustcAbC123"
+fn is_rustc_tempdir_name(name: &str) -> bool {
+    name.strip_prefix("rustc")
+        .is_some_and(|suffix| suffix.len() == 6 && suffix.chars().all(char::is_alphanumeric))
+}
+
+fn remove_stale_rustc_tempdirs(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_rustc_tempdir_name)
+                && path.is_dir()
+                && is_old(path)
+        })
+        .for_each(|path| {
+            let _ = std::fs::remove_dir_all(path);
+        });
+}
+
+/// free space on the filesystem of `path`
+fn free_disk_kb(path: &Path) -> Option<u64> {
+    let output = Command::new("df")
+        .args(["--output=avail", "-k"])
+        .arg(path)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .nth(1)?
+        .trim()
+        .parse::<u64>()
+        .ok()
+}
+
+/// blocks while the disk of the run tempdir has less than --min-free-disk left
+pub(crate) fn wait_for_disk_space() {
+    let Some(run_tempdir) = RUN_TEMPDIR.get() else {
+        return;
+    };
+    // everyone else waits here while we are paused
+    let mut last_check = LAST_DISK_CHECK.lock().unwrap();
+    if last_check.is_some_and(|last_check| last_check.elapsed() < DISK_CHECK_INTERVAL) {
+        return;
+    }
+    let mut paused = false;
+    while let Some(free_kb) = free_disk_kb(run_tempdir).filter(|kb| *kb < *MIN_FREE_DISK_KB) {
+        if !paused {
+            eprintln!(
+                "only {} MB of disk space left in '{}', pausing until there are {} MB",
+                free_kb / 1000,
+                run_tempdir.display(),
+                *MIN_FREE_DISK_KB / 1000
+            );
+            paused = true;
+        }
+        std::thread::sleep(DISK_CHECK_INTERVAL);
+    }
+    if paused {
+        eprintln!("enough disk space again, resuming");
+    }
+    *last_check = Some(Instant::now());
+}
+
+/// `path` if it is inside the run tempdir, otherwise the same file name in `confined_dir`
+fn confine_path(path: &str, current_dir: &Path, run_tempdir: &Path, confined_dir: &Path) -> String {
+    let escapes = Path::new(path)
+        .components()
+        .any(|component| component == Component::ParentDir);
+    if path == "/dev/null" || !escapes && current_dir.join(path).starts_with(run_tempdir) {
+        return path.to_string();
+    }
+    let file_name = Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("out");
+    confined_dir.join(file_name).display().to_string()
+}
+
+/// the args, with every output path of -o, --emit and OUTPUT_PATH_FLAGS inside the run tempdir.
+/// Paths that point elsewhere are moved into `confined_dir`, which belongs to this one process so
+/// that processes which run at the same time do not write to the same file
+pub(crate) fn confine_args(
+    args: &[OsString],
+    current_dir: &Path,
+    run_tempdir: &Path,
+    confined_dir: &Path,
+) -> Vec<OsString> {
+    let confine = |path: &str| confine_path(path, current_dir, run_tempdir, confined_dir);
+    let mut confined = Vec::with_capacity(args.len());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.to_str() else {
+            confined.push(arg.clone());
+            continue;
+        };
+        if flag == "-o" || flag == "--out-dir" {
+            // -o path
+            confined.push(arg.clone());
+            if let Some(path) = args.next() {
+                confined.push(
+                    path.to_str()
+                        .map_or_else(|| path.clone(), |p| confine(p).into()),
+                );
+            }
+        } else if let Some(path) = flag.strip_prefix("-o") {
+            confined.push(format!("-o{}", confine(path)).into());
+        } else if let Some(emit) = flag.strip_prefix("--emit=") {
+            // --emit=mir=path,link
+            let kinds = emit
+                .split(',')
+                .map(|kind| match kind.split_once('=') {
+                    Some((kind, path)) => format!("{kind}={}", confine(path)),
+                    None => kind.to_string(),
+                })
+                .collect::<Vec<String>>();
+            confined.push(format!("--emit={}", kinds.join(",")).into());
+        } else if let Some((name, path)) = flag
+            .split_once('=')
+            .filter(|(name, _)| OUTPUT_PATH_FLAGS.contains(name))
+        {
+            confined.push(format!("{name}={}", confine(path)).into());
+        } else {
+            confined.push(arg.clone());
+        }
+    }
+    confined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confined() {
+        let run_tempdir = Path::new("/tmp/icemaker_global_tempdir.abc");
+        let current_dir = run_tempdir.join("rustc_testrunner_tmpdir.def");
+        let args = [
+            "-o/home/user/out",
+            "-ocodegen",
+            "-o",
+            "../../x",
+            "--emit=mir=/tmp/file.mir,link",
+            "-Cprofile-generate=/tmp/icemaker_pgo/",
+            "-Zdump-mir-dir=/dev/null",
+            "-Zmir-opt-level=5",
+        ]
+        .map(OsString::from);
+        assert_eq!(
+            confine_args(&args, &current_dir, run_tempdir, &current_dir),
+            [
+                "-o/tmp/icemaker_global_tempdir.abc/rustc_testrunner_tmpdir.def/out",
+                "-ocodegen",
+                "-o",
+                "/tmp/icemaker_global_tempdir.abc/rustc_testrunner_tmpdir.def/x",
+                "--emit=mir=/tmp/icemaker_global_tempdir.abc/rustc_testrunner_tmpdir.def/file.mir,link",
+                "-Cprofile-generate=/tmp/icemaker_global_tempdir.abc/rustc_testrunner_tmpdir.def/icemaker_pgo",
+                "-Zdump-mir-dir=/dev/null",
+                "-Zmir-opt-level=5",
+            ]
+            .map(OsString::from)
+        );
+    }
+
+    #[test]
+    fn rustc_tempdir_names() {
+        assert!(is_rustc_tempdir_name("rustcAbC123"));
+        assert!(!is_rustc_tempdir_name("rustc_testrunner_tmpdir"));
+        assert!(!is_rustc_tempdir_name("rustc"));
+    }
+}
@@ -0,0 +1,407 @@
+// per (file, executable, flag set) timings
+//
+// Timer only knows how much time was spent per executable in total, this keeps every single
+// sample so that we can find out which files and which flag chunks are slow and which files
+// keep running into the timeout (candidates for EXCEPTIONS and MIRI_EXCEPTIONS)
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ice::{Executable, REPORTS_DIR};
+
+/// history of the previous runs, kept in the cwd next to errors_tmp.json
+pub(crate) const TIMINGS_HISTORY_FILE: &str = "icemaker_timings.json";
+
+/// how many entries of each category we print to the terminal
+const PRINT_TOP: usize = 15;
+
+#[derive(Debug, Clone)]
+struct Sample {
+    file: u32,
+    executable: Executable,
+    flags: u32,
+    millis: u32,
+    timed_out: bool,
+    // the sample turned up a finding
+    found: bool,
+}
+
+#[derive(Default)]
+struct Interned {
+    files: Vec<PathBuf>,
+    file_ids: HashMap<PathBuf, u32>,
+    flag_sets: Vec<String>,
+    flag_ids: HashMap<String, u32>,
+    samples: Vec<Sample>,
+}
+
+#[derive(Default)]
+pub(crate) struct TimingRecorder {
+    inner: Mutex<Interned>,
+}
+
+// we do not want to dump thousands of samples when printing the Timer
+impl std::fmt::Debug for TimingRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "TimingRecorder {{ samples: {} }}", self.len())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Stats {
+    pub(crate) median_ms: u32,
+    pub(crate) p95_ms: u32,
+    pub(crate) max_ms: u32,
+    pub(crate) samples: usize,
+}
+
+impl Stats {
+    fn from_millis(mut millis: Vec<u32>) -> Self {
+        if millis.is_empty() {
+            return Stats::default();
+        }
+        millis.sort_unstable();
+        let percentile = |p: usize| millis[((millis.len() - 1) * p) / 100];
+        Stats {
+            median_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: *millis.last().unwrap(),
+            samples: millis.len(),
+        }
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "median {:.1}s p95 {:.1}s max {:.1}s ({} runs)",
+            self.median_ms as f64 / 1000.0,
+            self.p95_ms as f64 / 1000.0,
+            self.max_ms as f64 / 1000.0,
+            self.samples
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct FileTimingHistory {
+    // stats of the most recent run
+    pub(crate) stats: Stats,
+    // number of runs the file was checked in
+    pub(crate) runs: u32,
+    // number of runs in which at least one non-miri executable timed out
+    pub(crate) timeout_runs: u32,
+    // number of runs in which miri timed out
+    pub(crate) miri_timeout_runs: u32,
+}
+
+impl FileTimingHistory {
+    fn routinely_times_out(&self) -> bool {
+        self.timeout_runs > 0 && self.timeout_runs * 2 >= self.runs
+    }
+
+    fn routinely_times_out_in_miri(&self) -> bool {
+        self.miri_timeout_runs > 0 && self.miri_timeout_runs * 2 >= self.runs
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct HitRate {
+    pub(crate) runs: u32,
+    pub(crate) hits: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct TimingHistory {
+    pub(crate) files: BTreeMap<PathBuf, FileTimingHistory>,
+    // "{executable:?} {flags}" => stats of the most recent run
+    pub(crate) flag_sets: BTreeMap<String, Stats>,
+    // "{executable:?} {flags}" => how often the flag chunk found something, over all runs
+    #[serde(default)]
+    pub(crate) flag_set_hits: BTreeMap<String, HitRate>,
+}
+
+impl TimingHistory {
+    pub(crate) fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// key under which the timings of a flag chunk are grouped
+pub(crate) fn flag_set_key(executable: &Executable, flags: &str) -> String {
+    format!("{executable:?} {flags}").trim_end().to_string()
+}
+
+/// "tests/ui/foo.rs" if the file is inside one of the projects
+fn project_relative<'a>(file: &'a Path, projects: &[PathBuf]) -> &'a Path {
+    projects
+        .iter()
+        .find_map(|project| file.strip_prefix(project).ok())
+        .unwrap_or(file)
+}
+
+impl TimingRecorder {
+    pub(crate) fn record(
+        &self,
+        file: &Path,
+        executable: &Executable,
+        flags: &[&str],
+        millis: u128,
+        timed_out: bool,
+        found: bool,
+    ) {
+        let flags = flags.join(" ");
+        let mut inner = self.inner.lock().unwrap();
+
+        let file = match inner.file_ids.get(file) {
+            Some(id) => *id,
+            None => {
+                let id = inner.files.len() as u32;
+                inner.files.push(file.to_path_buf());
+                inner.file_ids.insert(file.to_path_buf(), id);
+                id
+            }
+        };
+        let flags = match inner.flag_ids.get(&flags) {
+            Some(id) => *id,
+            None => {
+                let id = inner.flag_sets.len() as u32;
+                inner.flag_sets.push(flags.clone());
+                inner.flag_ids.insert(flags, id);
+                id
+            }
+        };
+
+        inner.samples.push(Sample {
+            file,
+            executable: executable.clone(),
+            flags,
+            millis: millis.min(u32::MAX as u128) as u32,
+            timed_out,
+            found,
+        });
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().samples.len()
+    }
+
+    /// all executables that were run at least once
+    pub(crate) fn executables(&self) -> Vec<Executable> {
+        let mut executables = Vec::new();
+        self.inner
+            .lock()
+            .unwrap()
+            .samples
+            .iter()
+            .for_each(|sample| {
+                if !executables.contains(&sample.executable) {
+                    executables.push(sample.executable.clone());
+                }
+            });
+        executables
+    }
+
+    /// print the slowest files and flag chunks, write the full report into the REPORTS_DIR,
+    /// update the timing history and, if requested, write candidates for the exception lists
+    pub(crate) fn report(&self, projects: &[PathBuf], history_path: &Path, write_candidates: bool) {
+        let inner = self.inner.lock().unwrap();
+        if inner.samples.is_empty() {
+            return;
+        }
+
+        let mut by_file: HashMap<u32, Vec<&Sample>> = HashMap::new();
+        let mut by_flag_set: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut flag_set_hits: HashMap<String, HitRate> = HashMap::new();
+        let mut by_run: HashMap<(u32, &Executable, u32), Vec<u32>> = HashMap::new();
+        for sample in &inner.samples {
+            by_file.entry(sample.file).or_default().push(sample);
+            by_run
+                .entry((sample.file, &sample.executable, sample.flags))
+                .or_default()
+                .push(sample.millis);
+            let key = flag_set_key(&sample.executable, &inner.flag_sets[sample.flags as usize]);
+            let hit_rate = flag_set_hits.entry(key.clone()).or_default();
+            hit_rate.runs += 1;
+            hit_rate.hits += u32::from(sample.found);
+            by_flag_set.entry(key).or_default().push(sample.millis);
+        }
+
+        let mut file_stats = by_file
+            .iter()
+            .map(|(file, samples)| {
+                let stats = Stats::from_millis(samples.iter().map(|s| s.millis).collect());
+                (&inner.files[*file as usize], stats, samples)
+            })
+            .collect::<Vec<_>>();
+        file_stats.sort_by_key(|(_, stats, _)| std::cmp::Reverse((stats.p95_ms, stats.max_ms)));
+
+        let mut flag_set_stats = by_flag_set
+            .into_iter()
+            .map(|(key, millis)| (key, Stats::from_millis(millis)))
+            .collect::<Vec<(String, Stats)>>();
+        flag_set_stats.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.median_ms));
+
+        // a file can be slow with one flag chunk only, this is where that shows up
+        let mut run_stats = by_run
+            .into_iter()
+            .map(|((file, executable, flags), millis)| {
+                (
+                    format!(
+                        "{}  {}",
+                        inner.files[file as usize].display(),
+                        flag_set_key(executable, &inner.flag_sets[flags as usize])
+                    ),
+                    Stats::from_millis(millis),
+                )
+            })
+            .collect::<Vec<(String, Stats)>>();
+        run_stats.sort_by_key(|(_, stats)| std::cmp::Reverse((stats.max_ms, stats.median_ms)));
+
+        let mut report = String::from("slowest files:\n");
+        file_stats.iter().for_each(|(file, stats, _)| {
+            report.push_str(&format!("{stats}  {}\n", file.display()));
+        });
+        report.push_str("\nslowest flag chunks:\n");
+        flag_set_stats.iter().for_each(|(key, stats)| {
+            report.push_str(&format!("{stats}  {key}\n"));
+        });
+        report.push_str("\nslowest file, executable and flag chunk combinations:\n");
+        run_stats.iter().for_each(|(key, stats)| {
+            report.push_str(&format!("{stats}  {key}\n"));
+        });
+
+        eprintln!("\nSlowest files:");
+        file_stats
+            .iter()
+            .take(PRINT_TOP)
+            .for_each(|(file, stats, _)| eprintln!("{stats}  {}", file.display()));
+        eprintln!("\nSlowest flag chunks:");
+        flag_set_stats
+            .iter()
+            .take(PRINT_TOP)
+            .for_each(|(key, stats)| eprintln!("{stats}  {key}"));
+        eprintln!("\nSlowest file, executable and flag chunk combinations:");
+        run_stats
+            .iter()
+            .take(PRINT_TOP)
+            .for_each(|(key, stats)| eprintln!("{stats}  {key}"));
+
+        let reports_dir = REPORTS_DIR.to_owned();
+        if std::fs::create_dir_all(&reports_dir).is_ok() {
+            let report_path = reports_dir.join("timings.txt");
+            if std::fs::write(&report_path, report).is_ok() {
+                eprintln!("full timing report: {}", report_path.display());
+            }
+        }
+
+        // merge this run into the history
+        let mut history = TimingHistory::load(history_path);
+        for (file, stats, samples) in &file_stats {
+            let entry = history.files.entry(file.to_path_buf()).or_default();
+            entry.stats = *stats;
+            entry.runs += 1;
+            if samples
+                .iter()
+                .any(|s| s.timed_out && !matches!(s.executable, Executable::Miri))
+            {
+                entry.timeout_runs += 1;
+            }
+            if samples
+                .iter()
+                .any(|s| s.timed_out && matches!(s.executable, Executable::Miri))
+            {
+                entry.miri_timeout_runs += 1;
+            }
+        }
+        history.flag_sets.extend(flag_set_stats);
+        for (key, hit_rate) in flag_set_hits {
+            let entry = history.flag_set_hits.entry(key).or_default();
+            entry.runs += hit_rate.runs;
+            entry.hits += hit_rate.hits;
+        }
+
+        if let Ok(json) = serde_json::to_string_pretty(&history) {
+            if std::fs::write(history_path, json).is_err() {
+                eprintln!("failed to write {}", history_path.display());
+            }
+        }
+
+        if write_candidates {
+            write_exception_candidates(&history, projects);
+        }
+    }
+}
+
+/// write files that timed out in at least half of the runs as rust source that can be pasted
+/// into flags.rs
+fn write_exception_candidates(history: &TimingHistory, projects: &[PathBuf]) {
+    let candidates = |filter: fn(&FileTimingHistory) -> bool| {
+        history
+            .files
+            .iter()
+            .filter(|(_, file_history)| filter(file_history))
+            .map(|(file, file_history)| {
+                format!(
+                    "    \"{}\", // timed out in {}/{} runs, max {:.1}s\n",
+                    project_relative(file, projects).display(),
+                    file_history
+                        .timeout_runs
+                        .max(file_history.miri_timeout_runs),
+                    file_history.runs,
+                    file_history.stats.max_ms as f64 / 1000.0
+                )
+            })
+            .collect::<String>()
+    };
+
+    let exceptions = candidates(FileTimingHistory::routinely_times_out);
+    let miri_exceptions = candidates(FileTimingHistory::routinely_times_out_in_miri);
+
+    let content = format!(
+        "// candidates generated from {TIMINGS_HISTORY_FILE}, check before adding to flags.rs\n\npub(crate) static EXCEPTIONS: &[&str] = &[\n{exceptions}];\n\npub(crate) static MIRI_EXCEPTIONS: &[&str] = &[\n{miri_exceptions}];\n"
+    );
+
+    let path = REPORTS_DIR.join("exceptions_candidates.rs");
+    match std::fs::create_dir_all(REPORTS_DIR.as_path())
+        .and_then(|_| std::fs::write(&path, content))
+    {
+        Ok(()) => eprintln!("exception list candidates: {}", path.display()),
+        Err(e) => eprintln!("failed to write {}: {e}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_percentiles() {
+        let stats = Stats::from_millis((1..=100).rev().collect());
+        assert_eq!(stats.median_ms, 50);
+        assert_eq!(stats.p95_ms, 95);
+        assert_eq!(stats.max_ms, 100);
+        assert_eq!(stats.samples, 100);
+
+        assert_eq!(Stats::from_millis(Vec::new()), Stats::default());
+    }
+
+    #[test]
+    fn timeout_candidates() {
+        let history = FileTimingHistory {
+            runs: 4,
+            timeout_runs: 2,
+            miri_timeout_runs: 1,
+            ..Default::default()
+        };
+        assert!(history.routinely_times_out());
+        assert!(!history.routinely_times_out_in_miri());
+    }
+}
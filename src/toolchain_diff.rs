@@ -0,0 +1,205 @@
+// which findings appear or disappear between two toolchains, see --diff-toolchains
+//
+// the corpus is checked on the baseline. A file that crashes there exactly as in the errors.json is
+// known and skipped, every other file (clean on the baseline, or crashing differently) is checked on
+// the candidate as well. A finding without an equal finding in the results of
+// the other toolchain is run there once more with its own flags: panic locations move and flag
+// minimization may settle on different flags, only if the other toolchain does not crash the same
+// way the finding is a regression (or a fix).
+
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tempdir::TempDir;
+
+use crate::bisect::{reproduces_on, Toolchain};
+use crate::ice::ICE;
+use crate::reduce::is_reducible;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolchainChange {
+    // crashes on the candidate, but not on the baseline
+    Regressed,
+    // crashes on the baseline, but not on the candidate anymore
+    Fixed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolchainDiff {
+    pub(crate) change: ToolchainChange,
+    // toolchain A
+    pub(crate) baseline: Toolchain,
+    // toolchain B
+    pub(crate) candidate: Toolchain,
+}
+
+impl std::fmt::Display for ToolchainDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let change = match self.change {
+            ToolchainChange::Regressed => "regressed",
+            ToolchainChange::Fixed => "fixed",
+        };
+        write!(f, "{change} from {} to {}", self.baseline, self.candidate)
+    }
+}
+
+/// the rustup toolchain with that name
+pub(crate) fn rustup_toolchain(name: &str) -> Toolchain {
+    let dir = home::rustup_home()
+        .expect("could not find the rustup home")
+        .join("toolchains")
+        .join(name);
+    Toolchain::new(name.to_string(), &dir)
+}
+
+/// the files to check on the candidate toolchain. `relative` turns a file into the path of the
+/// errors.json, `baseline_findings` already use these paths
+pub(crate) fn files_for_candidate(
+    files: &[PathBuf],
+    relative: impl Fn(&Path) -> PathBuf,
+    baseline_findings: &[ICE],
+    recorded: &[ICE],
+) -> Vec<PathBuf> {
+    files
+        .iter()
+        .filter(|file| {
+            let file = relative(file);
+            let found = baseline_findings
+                .iter()
+                .filter(|ice| ice.file == file)
+                .collect::<Vec<&ICE>>();
+            let known = recorded
+                .iter()
+                .filter(|ice| ice.file == file)
+                .collect::<Vec<&ICE>>();
+            // clean on the baseline, the candidate may crash
+            found.is_empty()
+                || found.len() != known.len()
+                || found.iter().any(|ice| !known.contains(ice))
+        })
+        .cloned()
+        .collect()
+}
+
+/// the findings that have no counterpart in `others` and do not crash the same way on the other
+/// toolchain either
+fn only_in<'a>(
+    findings: &'a [ICE],
+    others: &[ICE],
+    other_toolchain: &str,
+    global_tempdir_path: &Path,
+) -> Vec<&'a ICE> {
+    let tempdir = TempDir::new_in(global_tempdir_path, "icemaker_toolchain_diff_tempdir").unwrap();
+    findings
+        .par_iter()
+        // hangs and OOMs come and go with the load of the machine
+        .filter(|ice| is_reducible(ice) && !others.contains(ice))
+        .filter(|ice| {
+            let Some(binary) = ice.executable.path_in_toolchain(other_toolchain) else {
+                return false;
+            };
+            let Ok(file) = ice.file.canonicalize() else {
+                return false;
+            };
+            !reproduces_on(ice, &binary, &file, tempdir.path())
+        })
+        .collect()
+}
+
+/// findings that regressed from the baseline to the candidate toolchain and the ones that got
+/// fixed, tagged with both toolchains
+pub(crate) fn diff_findings(
+    (baseline_name, baseline_findings): (&str, &[ICE]),
+    (candidate_name, candidate_findings): (&str, &[ICE]),
+    global_tempdir_path: &Path,
+) -> Vec<ICE> {
+    let baseline = rustup_toolchain(baseline_name);
+    let candidate = rustup_toolchain(candidate_name);
+    let tag = |ice: &ICE, change: ToolchainChange| ICE {
+        toolchain_diff: Some(ToolchainDiff {
+            change,
+            baseline: baseline.clone(),
+            candidate: candidate.clone(),
+        }),
+        ..ice.clone()
+    };
+
+    let regressed = only_in(
+        candidate_findings,
+        baseline_findings,
+        baseline_name,
+        global_tempdir_path,
+    );
+    let fixed = only_in(
+        baseline_findings,
+        candidate_findings,
+        candidate_name,
+        global_tempdir_path,
+    );
+    eprintln!(
+        "{} regressions and {} fixes from {baseline} to {candidate}",
+        regressed.len(),
+        fixed.len()
+    );
+
+    regressed
+        .into_iter()
+        .map(|ice| tag(ice, ToolchainChange::Regressed))
+        .chain(
+            fixed
+                .into_iter()
+                .map(|ice| tag(ice, ToolchainChange::Fixed)),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidate_files() {
+        let ice = |file: &str, msg: &str| ICE {
+            file: PathBuf::from(file),
+            ice_msg: msg.to_string(),
+            ..Default::default()
+        };
+        let files = ["clean.rs", "known.rs", "changed.rs", "new.rs"].map(PathBuf::from);
+        let recorded = [ice("known.rs", "a"), ice("changed.rs", "b")];
+        // crashes on the baseline
+        let baseline = [
+            ice("known.rs", "a"),
+            ice("changed.rs", "c"),
+            ice("new.rs", "d"),
+        ];
+        // only the candidate crashes on clean.rs, it has to run there
+        let candidate_files = files_for_candidate(&files, Path::to_path_buf, &baseline, &recorded);
+        assert_eq!(
+            candidate_files,
+            ["clean.rs", "changed.rs", "new.rs"].map(PathBuf::from)
+        );
+        // nothing crashes on the baseline, everything goes to the candidate
+        assert_eq!(
+            files_for_candidate(&files, Path::to_path_buf, &[], &recorded),
+            files
+        );
+    }
+
+    #[test]
+    fn display() {
+        let toolchain = |name: &str, commit_hash: Option<&str>| Toolchain {
+            name: name.to_string(),
+            commit_hash: commit_hash.map(str::to_string),
+        };
+        let diff = ToolchainDiff {
+            change: ToolchainChange::Regressed,
+            baseline: toolchain("master-old", Some("abc")),
+            candidate: toolchain("master", None),
+        };
+        assert_eq!(
+            diff.to_string(),
+            "regressed from master-old (abc) to master"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// suggested rust-lang/rust labels and the compiler crate that most likely owns a finding,
+// so that findings can be routed to the right people without reading every backtrace
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::debug_assertions::AssertionSensitivity;
+use crate::ice::{AcceptReject, AcceptanceChange, Executable, ICEKind, ICE};
+
+// #![feature(foo, bar)]
+static FEATURE_ATTR_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"#!\s*\[\s*feature\s*\(([^)]*)\)\s*\]").unwrap());
+
+// -Zcrate-attr=feature(foo)
+static CRATE_ATTR_FEATURE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^-Zcrate-attr=feature\(([^)]*)\)$").unwrap());
+
+/// flag prefix => area label
+static AREA_FLAGS: &[(&str, &str)] = &[
+    ("-Cincremental", "A-incr-comp"),
+    ("-Zincremental", "A-incr-comp"),
+    ("INCR_COMP", "A-incr-comp"),
+    ("-Zmir-opt-level", "A-mir-opt"),
+    ("-Zinline-mir", "A-mir-opt-inlining"),
+    ("-Zpolonius", "NLL-polonius"),
+    ("-Znext-solver", "WG-trait-system-refactor"),
+    ("-Zvalidate-mir", "A-MIR"),
+    ("-Cinstrument-coverage", "A-code-coverage"),
+    ("-Zsanitizer", "A-sanitizers"),
+    ("-Cdebuginfo", "A-debuginfo"),
+    ("-Zunpretty", "A-pretty"),
+    ("-Zthreads", "WG-compiler-parallel"),
+];
+
+fn team_label(executable: &Executable) -> Option<&'static str> {
+    match executable {
+        Executable::Rustc | Executable::RustFix => Some("T-compiler"),
+        Executable::Rustdoc => Some("T-rustdoc"),
+        Executable::Clippy | Executable::ClippyFix => Some("T-clippy"),
+        Executable::Rustfmt => Some("T-rustfmt"),
+        Executable::Miri => Some("T-miri"),
+        _ => None,
+    }
+}
+
+fn kind_label(kind: &ICEKind) -> Option<&'static str> {
+    match kind {
+        ICEKind::Ice(_) | ICEKind::DoubleIce => Some("I-ICE"),
+        ICEKind::Hang(_) => Some("I-hang"),
+        ICEKind::OOM => Some("I-compilemem"),
+        // --accept-reject compares stable with master, not with a nightly
+        ICEKind::AcceptReject(AcceptReject {
+            change: AcceptanceChange::NewlyRejected,
+            ..
+        }) => Some("regression-from-stable-to-master"),
+        _ => None,
+    }
+}
+
+/// F-foo for every feature enabled via -Zcrate-attr or #![feature] in the source
+fn feature_labels(args: &[String], source: Option<&str>) -> Vec<String> {
+    let from_flags = args
+        .iter()
+        .filter_map(|arg| CRATE_ATTR_FEATURE_REGEX.captures(arg))
+        .map(|captures| captures[1].to_string());
+    let from_source = source.into_iter().flat_map(enabled_features);
+
+    from_flags
+        .flat_map(|features| split_features(&features))
+        .chain(from_source)
+        .map(|feature| format!("F-{feature}"))
+        .collect()
+}
+
+fn split_features(features: &str) -> Vec<String> {
+    features
+        .split(',')
+        .map(|feature| feature.trim().to_string())
+        .filter(|feature| !feature.is_empty())
+        .collect()
+}
+
+/// all features enabled by #![feature] attributes in the source
+pub(crate) fn enabled_features(source: &str) -> Vec<String> {
+    FEATURE_ATTR_REGEX
+        .captures_iter(source)
+        .flat_map(|captures| split_features(&captures[1]))
+        .collect()
+}
+
+/// compiler/rustc_hir_typeck/src/expr.rs:1:2 => rustc_hir_typeck
+pub(crate) fn component_from_location(location: &str) -> Option<String> {
+    let path = location.split(':').next()?;
+    let mut components = path.split('/').skip_while(|component| {
+        !matches!(
+            *component,
+            "compiler" | "clippy_lints" | "clippy_utils" | "librustdoc" | "rustfmt"
+        )
+    });
+    match components.next()? {
+        "compiler" => components.next().map(str::to_string),
+        // tools that are not inside of compiler/
+        tool => Some(tool.to_string()),
+    }
+}
+
+/// `editions` are the editions the finding reproduces on, if it does not reproduce on all of them
+pub(crate) fn labels_for(
+    executable: &Executable,
+    kind: &ICEKind,
+    args: &[String],
+    source: Option<&str>,
+    editions: &[String],
+) -> Vec<String> {
+    let mut labels = Vec::new();
+    labels.extend(kind_label(kind).map(str::to_string));
+    labels.extend(team_label(executable).map(str::to_string));
+    labels.extend(feature_labels(args, source));
+    AREA_FLAGS
+        .iter()
+        .filter(|(flag, _)| args.iter().any(|arg| arg.starts_with(flag)))
+        .for_each(|(_, label)| labels.push(label.to_string()));
+    labels.extend(
+        editions
+            .iter()
+            .map(|edition| format!("A-edition-{edition}")),
+    );
+
+    // keep the order but drop duplicates such as A-incr-comp from several incr flags
+    let mut seen = std::collections::HashSet::new();
+    labels.retain(|label| seen.insert(label.clone()));
+    labels
+}
+
+impl ICE {
+    /// fill in the suggested labels and the owning compiler crate
+    pub(crate) fn with_triage(mut self) -> Self {
+        let source = std::fs::read_to_string(&self.file).ok();
+        let editions = if self.is_edition_specific() {
+            self.editions.as_slice()
+        } else {
+            &[]
+        };
+        self.labels = labels_for(
+            &self.executable,
+            &self.kind,
+            &self.args,
+            source.as_deref(),
+            editions,
+        );
+        if self.debug_assertions == Some(AssertionSensitivity::DebugAssertionsOnly) {
+            self.labels.push(String::from("requires-debug-assertions"));
+        }
+        self.component = self
+            .panic_location()
+            .as_deref()
+            .and_then(component_from_location);
+        self
+    }
+
+    /// "I-ICE T-compiler F-foo (rustc_hir_typeck)", empty if we have nothing to say
+    pub(crate) fn triage_line(&self) -> String {
+        let labels = self.labels.join(" ");
+        match &self.component {
+            Some(component) if labels.is_empty() => format!("({component})"),
+            Some(component) => format!("{labels} ({component})"),
+            None => labels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ice::Interestingness;
+
+    #[test]
+    fn labels_from_flags_and_source() {
+        let args = vec![
+            String::from("-Zcrate-attr=feature(generic_const_exprs)"),
+            String::from("-Cincremental=/tmp/incr"),
+            String::from("-Zincremental-verify-ich=yes"),
+            String::from("-Zmir-opt-level=5"),
+        ];
+        let source = "#![feature(adt_const_params, min_specialization)]\nfn main() {}";
+        let labels = labels_for(
+            &Executable::Rustc,
+            &ICEKind::Ice(Interestingness::Interesting),
+            &args,
+            Some(source),
+            &[String::from("2021")],
+        );
+        assert_eq!(
+            labels,
+            [
+                "I-ICE",
+                "T-compiler",
+                "F-generic_const_exprs",
+                "F-adt_const_params",
+                "F-min_specialization",
+                "A-incr-comp",
+                "A-mir-opt",
+                "A-edition-2021"
+            ]
+        );
+    }
+
+    #[test]
+    fn component() {
+        assert_eq!(
+            component_from_location("compiler/rustc_hir_typeck/src/expr.rs:123:45").as_deref(),
+            Some("rustc_hir_typeck")
+        );
+        assert_eq!(
+            component_from_location("/rustc/abcdef/compiler/rustc_middle/src/ty/mod.rs:1:1")
+                .as_deref(),
+            Some("rustc_middle")
+        );
+        assert_eq!(
+            component_from_location("src/tools/clippy/clippy_lints/src/foo.rs:1:1").as_deref(),
+            Some("clippy_lints")
+        );
+        assert_eq!(
+            component_from_location("library/core/src/option.rs:1:1"),
+            None
+        );
+    }
+}